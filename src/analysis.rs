@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use crate::{GowsimError, State};
+
+/// Most common values of the histogram in ascending order, more than one if they tie
+pub fn modes(state: &State) -> Vec<usize> {
+    let Some(max_count) = state.values().copied().max() else {
+        return Vec::new();
+    };
+    let mut modes: Vec<usize> = state
+        .iter()
+        .filter(|(_, count)| **count == max_count)
+        .map(|(value, _)| *value)
+        .collect();
+    modes.sort_unstable();
+    modes
+}
+
+/// Splits off the values seen fewer than `threshold` times, returning the remaining histogram
+/// and the total count of the removed values
+pub fn collapse_tail(state: &State, threshold: u64) -> (State, u64) {
+    let mut kept = State::new();
+    let mut other = 0;
+    for (value, count) in state {
+        if *count < threshold {
+            other += count;
+        } else {
+            kept.insert(*value, *count);
+        }
+    }
+    (kept, other)
+}
+
+/// Keeps the values from `min` to `max`, both included, returning them with the total counts of
+/// the values below and above
+pub fn window(state: &State, min: usize, max: usize) -> (State, u64, u64) {
+    let mut kept = State::new();
+    let (mut underflow, mut overflow) = (0, 0);
+    for (value, count) in state {
+        if *value < min {
+            underflow += count;
+        } else if *value > max {
+            overflow += count;
+        } else {
+            kept.insert(*value, *count);
+        }
+    }
+    (kept, underflow, overflow)
+}
+
+/// Rough number of bytes the histogram occupies in memory, one key, count and control byte
+/// per allocated slot of the map
+pub fn estimate_memory(state: &State) -> usize {
+    let entry_size = std::mem::size_of::<(usize, u64)>() + 1;
+    std::mem::size_of::<State>() + state.capacity() * entry_size
+}
+
+/// Empirical CDF as `(value, cumulative_count, cumulative_fraction)` in ascending order of
+/// value, the last fraction being exactly 1
+pub fn cdf(state: &State) -> Vec<(usize, u64, f64)> {
+    let mut values: Vec<(usize, u64)> = state.iter().map(|(k, v)| (*k, *v)).collect();
+    values.sort_unstable();
+    let total: u64 = values.iter().map(|(_, count)| count).sum();
+    let mut cumulative = 0;
+    values
+        .into_iter()
+        .map(|(value, count)| {
+            cumulative += count;
+            (value, cumulative, cumulative as f64 / total as f64)
+        })
+        .collect()
+}
+
+/// Geometric mean of the positive values, weighted by their counts, `None` without any
+///
+/// Values of 0 are left out, as they would make the mean 0.
+pub fn geometric_mean(state: &State) -> Option<f64> {
+    let (sum, total) = positive_values(state).fold((0f64, 0f64), |(sum, total), (value, count)| {
+        (sum + count * value.ln(), total + count)
+    });
+    (total > 0.0).then(|| (sum / total).exp())
+}
+
+/// Harmonic mean of the positive values, weighted by their counts, `None` without any
+///
+/// Values of 0 are left out, as they have no reciprocal.
+pub fn harmonic_mean(state: &State) -> Option<f64> {
+    let (sum, total) = positive_values(state).fold((0f64, 0f64), |(sum, total), (value, count)| {
+        (sum + count / value, total + count)
+    });
+    (total > 0.0).then(|| total / sum)
+}
+
+fn positive_values(state: &State) -> impl Iterator<Item = (f64, f64)> + '_ {
+    state
+        .iter()
+        .filter(|(value, _)| **value > 0)
+        .map(|(value, count)| (*value as f64, *count as f64))
+}
+
+/// Smallest value at or below which at least the fraction `q` of the games lie, `None` for an
+/// empty histogram
+pub fn quantile(state: &State, q: f64) -> Option<usize> {
+    let cdf = cdf(state);
+    cdf.iter()
+        .find(|(_, _, fraction)| *fraction >= q)
+        .or(cdf.last())
+        .map(|(value, _, _)| *value)
+}
+
+/// Minimum, quartiles and maximum of a histogram, the data behind a box plot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FiveNumberSummary {
+    pub min: usize,
+    pub q1: usize,
+    pub median: usize,
+    pub q3: usize,
+    pub max: usize,
+}
+
+impl FiveNumberSummary {
+    /// Interquartile range, the distance between the first and the third quartile
+    pub fn iqr(&self) -> usize {
+        self.q3 - self.q1
+    }
+}
+
+/// Five number summary with the quartiles of [`quantile`], `None` for an empty histogram
+pub fn five_number_summary(state: &State) -> Option<FiveNumberSummary> {
+    Some(FiveNumberSummary {
+        min: *state.keys().min()?,
+        q1: quantile(state, 0.25)?,
+        median: quantile(state, 0.5)?,
+        q3: quantile(state, 0.75)?,
+        max: *state.keys().max()?,
+    })
+}
+
+/// Sample autocorrelation of `series` with itself `lag` steps later, from -1 to 1
+///
+/// Uses the usual estimator dividing by the variance of the whole series, `None` if the
+/// series is not longer than `lag` or does not vary at all.
+pub fn autocorrelation(series: &[f64], lag: usize) -> Option<f64> {
+    if series.len() <= lag {
+        return None;
+    }
+    let mean = series.iter().sum::<f64>() / series.len() as f64;
+    let variance: f64 = series.iter().map(|x| (x - mean) * (x - mean)).sum();
+    if variance == 0.0 {
+        return None;
+    }
+    let covariance: f64 = series
+        .iter()
+        .zip(&series[lag..])
+        .map(|(x, y)| (x - mean) * (y - mean))
+        .sum();
+    Some(covariance / variance)
+}
+
+/// Kolmogorov–Smirnov test of the histogram against a reference distribution given as weights
+/// per value, returning the statistic and its asymptotic p-value
+///
+/// The statistic is the largest distance between the two CDFs at any value of either
+/// distribution. For discrete data the p-value from the Kolmogorov distribution is
+/// conservative, too large rather than too small. Fails if either distribution is empty, as
+/// every fit against it would look perfect.
+pub fn ks_test(state: &State, reference: &HashMap<usize, f64>) -> Result<(f64, f64), GowsimError> {
+    let total: u64 = state.values().sum();
+    let reference_total: f64 = reference.values().sum();
+    if total == 0 {
+        return Err(GowsimError::InvalidConfig(
+            "The histogram to fit is empty".to_string(),
+        ));
+    }
+    if reference_total.is_nan() || reference_total <= 0.0 {
+        return Err(GowsimError::InvalidConfig(
+            "The reference weights do not sum to a positive total".to_string(),
+        ));
+    }
+    let mut values: Vec<usize> = state.keys().chain(reference.keys()).copied().collect();
+    values.sort_unstable();
+    values.dedup();
+    let (mut cumulative, mut reference_cumulative) = (0u64, 0f64);
+    let mut statistic = 0f64;
+    for value in values {
+        cumulative += state.get(&value).copied().unwrap_or(0);
+        reference_cumulative += reference.get(&value).copied().unwrap_or(0.0);
+        let distance =
+            (cumulative as f64 / total as f64 - reference_cumulative / reference_total).abs();
+        statistic = statistic.max(distance);
+    }
+    let sqrt_n = (total as f64).sqrt();
+    Ok((
+        statistic,
+        kolmogorov_q((sqrt_n + 0.12 + 0.11 / sqrt_n) * statistic),
+    ))
+}
+
+/// Survival function of the Kolmogorov distribution
+fn kolmogorov_q(lambda: f64) -> f64 {
+    if lambda < 0.2 {
+        // The series converges too slowly, while the survival function is within 1e-12 of 1 here
+        return 1.0;
+    }
+    let mut sum = 0f64;
+    for k in 1..=100 {
+        let term = (-2.0 * (k * k) as f64 * lambda * lambda).exp();
+        sum += if k % 2 == 1 { term } else { -term };
+        if term < 1e-12 {
+            break;
+        }
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tied_modes_are_all_returned_in_ascending_order() {
+        let state = State::from([(120, 7), (35, 2), (80, 7), (200, 1)]);
+        assert_eq!(modes(&state), [80, 120]);
+        assert_eq!(modes(&State::from([(4, 1)])), [4]);
+        assert!(modes(&State::new()).is_empty());
+    }
+
+    #[test]
+    fn collapsed_tail_keeps_the_total_count() {
+        let state = State::from([(40, 10), (41, 1), (90, 2), (300, 1), (55, 3)]);
+        let (kept, other) = collapse_tail(&state, 3);
+        assert_eq!(kept, State::from([(40, 10), (55, 3)]));
+        assert_eq!(other, 4);
+        assert_eq!(
+            kept.values().sum::<u64>() + other,
+            state.values().sum::<u64>()
+        );
+        assert_eq!(collapse_tail(&state, 0), (state.clone(), 0));
+    }
+
+    #[test]
+    fn cdf_is_monotonic_and_ends_at_one() {
+        let state = State::from([(30, 1), (10, 2), (20, 3), (50, 1)]);
+        let cdf = cdf(&state);
+        assert_eq!(
+            cdf.iter()
+                .map(|(value, count, _)| (*value, *count))
+                .collect::<Vec<_>>(),
+            [(10, 2), (20, 5), (30, 6), (50, 7)]
+        );
+        assert!(cdf.windows(2).all(|pair| pair[0].2 < pair[1].2));
+        assert_eq!(cdf.last().unwrap().2, 1.0);
+        assert_eq!(cdf[0].2, 2.0 / 7.0);
+        assert!(super::cdf(&State::new()).is_empty());
+    }
+
+    #[test]
+    fn ks_test_accepts_a_matching_histogram() {
+        let reference: HashMap<usize, f64> = (10..20).map(|value| (value, 0.5)).collect();
+        let state: State = (10..20).map(|value| (value, 100)).collect();
+        let (statistic, p_value) = ks_test(&state, &reference).unwrap();
+        assert!(statistic < 1e-12);
+        assert_eq!(p_value, 1.0);
+    }
+
+    #[test]
+    fn ks_test_rejects_a_shifted_histogram() {
+        let reference: HashMap<usize, f64> = (10..20).map(|value| (value, 1.0)).collect();
+        let state: State = (15..25).map(|value| (value, 100)).collect();
+        let (statistic, p_value) = ks_test(&state, &reference).unwrap();
+        assert!((statistic - 0.5).abs() < 1e-12);
+        assert!(p_value < 1e-6);
+        assert!((kolmogorov_q(1.36) - 0.049).abs() < 0.001);
+    }
+
+    #[test]
+    fn ks_test_refuses_empty_distributions() {
+        let reference = HashMap::from([(1, 1.0)]);
+        assert!(ks_test(&State::new(), &reference).is_err());
+        assert!(ks_test(&State::from([(1, 1)]), &HashMap::new()).is_err());
+        assert!(ks_test(&State::from([(1, 1)]), &HashMap::from([(1, f64::NAN)])).is_err());
+    }
+
+    #[test]
+    fn memory_estimate_scales_with_the_distinct_lengths() {
+        let histogram = |lengths: usize| -> State { (0..lengths).map(|l| (l, 1000)).collect() };
+        let empty = estimate_memory(&State::new());
+        assert_eq!(empty, std::mem::size_of::<State>());
+        let small = estimate_memory(&histogram(100));
+        let large = estimate_memory(&histogram(10_000));
+        assert!(small > empty + 100 * 16);
+        assert!(large > 50 * (small - empty));
+        // Counts do not take up more room
+        let heavy: State = (0..100).map(|l| (l, u64::MAX)).collect();
+        assert_eq!(estimate_memory(&heavy), small);
+    }
+
+    #[test]
+    fn quantiles_of_two_states_pair_up() {
+        let a = State::from([(10, 1), (20, 1), (30, 1), (40, 1)]);
+        let b = State::from([(100, 2), (200, 2)]);
+        let paired: Vec<(usize, usize)> = [0.0, 0.25, 0.5, 0.75, 1.0]
+            .into_iter()
+            .map(|q| (quantile(&a, q).unwrap(), quantile(&b, q).unwrap()))
+            .collect();
+        assert_eq!(
+            paired,
+            [(10, 100), (10, 100), (20, 100), (30, 200), (40, 200)]
+        );
+        assert_eq!(quantile(&State::new(), 0.5), None);
+    }
+
+    #[test]
+    fn means_of_a_known_histogram() {
+        let state = State::from([(1, 1), (4, 1), (16, 1)]);
+        let arithmetic = state
+            .iter()
+            .map(|(value, count)| (value * *count as usize) as f64)
+            .sum::<f64>()
+            / 3.0;
+        let geometric = geometric_mean(&state).unwrap();
+        let harmonic = harmonic_mean(&state).unwrap();
+        assert_eq!(arithmetic, 7.0);
+        assert!((geometric - 4.0).abs() < 1e-12);
+        assert!((harmonic - 48.0 / 21.0).abs() < 1e-12);
+        assert!(arithmetic > geometric && geometric > harmonic);
+
+        // Counts weight the values, games of length 0 are left out
+        let weighted = State::from([(0, 5), (2, 3), (8, 1)]);
+        let geometric = 2f64.powf(3.0 / 4.0) * 8f64.powf(1.0 / 4.0);
+        assert!((geometric_mean(&weighted).unwrap() - geometric).abs() < 1e-12);
+        assert!((harmonic_mean(&weighted).unwrap() - 4.0 / (1.5 + 0.125)).abs() < 1e-12);
+        assert_eq!(geometric_mean(&State::from([(0, 4)])), None);
+        assert_eq!(harmonic_mean(&State::new()), None);
+    }
+
+    #[test]
+    fn five_number_summary_of_known_histograms() {
+        let uniform: State = (1..=8).map(|length| (length, 1)).collect();
+        let summary = five_number_summary(&uniform).unwrap();
+        assert_eq!(
+            summary,
+            FiveNumberSummary {
+                min: 1,
+                q1: 2,
+                median: 4,
+                q3: 6,
+                max: 8,
+            }
+        );
+        assert_eq!(summary.iqr(), 4);
+
+        let skewed = State::from([(10, 1), (20, 6), (30, 1), (90, 2)]);
+        let summary = five_number_summary(&skewed).unwrap();
+        assert_eq!(
+            (
+                summary.min,
+                summary.q1,
+                summary.median,
+                summary.q3,
+                summary.max
+            ),
+            (10, 20, 20, 30, 90)
+        );
+        assert_eq!(summary.iqr(), 10);
+        assert_eq!(five_number_summary(&State::new()), None);
+    }
+
+    #[test]
+    fn window_sums_the_counts_outside_of_it() {
+        let state = State::from([(5, 2), (10, 3), (50, 7), (100, 1), (101, 4), (400, 2)]);
+        let (kept, underflow, overflow) = window(&state, 10, 100);
+        assert_eq!(kept, State::from([(10, 3), (50, 7), (100, 1)]));
+        assert_eq!((underflow, overflow), (2, 6));
+        assert_eq!(
+            kept.values().sum::<u64>() + underflow + overflow,
+            state.values().sum::<u64>()
+        );
+        assert_eq!(window(&state, 0, usize::MAX), (state.clone(), 0, 0));
+        assert_eq!(window(&state, 200, 300), (State::new(), 17, 2));
+    }
+
+    #[test]
+    fn steadily_growing_lead_is_strongly_autocorrelated() {
+        use crate::game::{Card, Deck, Face, Game, GameRules, Suit};
+        // Player 0 is dealt every card from 9 to ace and wins each of the 24 turns
+        let mut cards = Vec::new();
+        let suits = [Suit::Hearts, Suit::Spades, Suit::Diamonds, Suit::Clubs];
+        for (low, high) in (2..=7).zip(9..=14) {
+            for suit in suits {
+                cards.push(Card::new(suit, Face::from_strength(high).unwrap()));
+                cards.push(Card::new(suit, Face::from_strength(low).unwrap()));
+            }
+        }
+        let mut game = Game::from_deck(Deck::from(cards), 0, GameRules::default());
+        let mut leads = Vec::new();
+        while game.step().is_some() {
+            leads.push(game.players.0.count_cards() as f64 - game.players.1.count_cards() as f64);
+        }
+        assert_eq!(leads.len(), 24);
+        assert!(leads.windows(2).all(|pair| pair[1] > pair[0]));
+        assert!(autocorrelation(&leads, 1).unwrap() > 0.8);
+        assert!(autocorrelation(&leads, 2).unwrap() > 0.7);
+
+        // Alternating leads are negatively correlated with the next turn
+        let alternating: Vec<f64> = (0..24).map(|turn| [2.0, -2.0][turn % 2]).collect();
+        assert!(autocorrelation(&alternating, 1).unwrap() < -0.9);
+        assert_eq!(autocorrelation(&[1.0; 10], 1), None);
+        assert_eq!(autocorrelation(&leads, 24), None);
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use crate::{game::Game, simulation::Recorder, GowsimError};
+
+/// Size in bytes of one [`GameRecord`]
+pub const RECORD_SIZE: usize = 16;
+
+/// Number of buffered bytes after which a [`BinaryLog`] writes to the shared writer
+const FLUSH_SIZE: usize = RECORD_SIZE * 4096;
+
+/// Summary of a single game, stored as a fixed width little endian record
+///
+/// The layout is the seed as `u64`, followed by the turn count and the war count as `u32`,
+/// saturating at `u32::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameRecord {
+    pub seed: u64,
+    pub turns: u32,
+    pub war_count: u32,
+}
+
+impl GameRecord {
+    pub fn from_game(seed: u64, game: &Game) -> Self {
+        GameRecord {
+            seed,
+            turns: game.stats.turn_number.try_into().unwrap_or(u32::MAX),
+            war_count: game.stats.war_count.try_into().unwrap_or(u32::MAX),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[0..8].copy_from_slice(&self.seed.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.turns.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.war_count.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Self {
+        GameRecord {
+            seed: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            turns: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            war_count: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+}
+
+/// Reads all records written by a [`BinaryLog`], failing on a truncated last record
+pub fn read_records(mut reader: impl Read) -> Result<Vec<GameRecord>, GowsimError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if bytes.len() % RECORD_SIZE != 0 {
+        return Err(GowsimError::Serialization(
+            "binary log ends with a truncated record".to_string(),
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(RECORD_SIZE)
+        .map(|chunk| GameRecord::from_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+/// Streams a [`GameRecord`] per game into a writer shared by all worker threads
+///
+/// Records are buffered per recorder and written in batches, so they end up in no particular
+/// order. Call [`BinaryLog::flush`] on the recorder returned by the simulation to write the
+/// remaining records and learn about any write error.
+#[derive(Debug)]
+pub struct BinaryLog<W> {
+    writer: Arc<Mutex<W>>,
+    buffer: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> BinaryLog<W> {
+    pub fn new(writer: Arc<Mutex<W>>) -> Self {
+        BinaryLog {
+            writer,
+            buffer: Vec::with_capacity(FLUSH_SIZE),
+            error: None,
+        }
+    }
+
+    fn write_buffer(&mut self) {
+        if self.error.is_some() || self.buffer.is_empty() {
+            return;
+        }
+        if let Err(error) = self.writer.lock().unwrap().write_all(&self.buffer) {
+            self.error = Some(error);
+        }
+        self.buffer.clear();
+    }
+
+    pub fn flush(mut self) -> io::Result<()> {
+        self.write_buffer();
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.writer.lock().unwrap().flush(),
+        }
+    }
+}
+
+impl<W: Write + Send> Recorder for BinaryLog<W> {
+    fn finish_game(&mut self, seed: u64, game: &Game) {
+        self.buffer
+            .extend_from_slice(&GameRecord::from_game(seed, game).to_bytes());
+        if self.buffer.len() >= FLUSH_SIZE {
+            self.write_buffer();
+        }
+    }
+
+    fn merge(&mut self, mut other: Self) {
+        other.write_buffer();
+        if self.error.is_none() {
+            self.error = other.error;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulation::StatsLog, Simulation};
+
+    #[test]
+    fn records_round_trip_through_bytes() {
+        let record = GameRecord {
+            seed: u64::MAX - 3,
+            turns: 1234,
+            war_count: u32::MAX,
+        };
+        assert_eq!(GameRecord::from_bytes(&record.to_bytes()), record);
+    }
+
+    #[test]
+    fn simulated_games_read_back_from_the_log() {
+        let writer = Arc::new(Mutex::new(Vec::new()));
+        let simulation = Simulation {
+            batch_size: 3,
+            ..Simulation::new(10, 40)
+        };
+        let (log, stats) = simulation
+            .run_with(
+                || (BinaryLog::new(writer.clone()), StatsLog::default()),
+                |_| {},
+            )
+            .unwrap();
+        log.flush().unwrap();
+        let bytes = writer.lock().unwrap().clone();
+        assert_eq!(bytes.len(), 10 * RECORD_SIZE);
+        let mut records = read_records(bytes.as_slice()).unwrap();
+        records.sort_unstable_by_key(|record| record.seed);
+        let expected: Vec<GameRecord> = stats
+            .stats
+            .iter()
+            .zip(40..)
+            .map(|(stats, seed)| GameRecord {
+                seed,
+                turns: stats.turn_number as u32,
+                war_count: stats.war_count as u32,
+            })
+            .collect();
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn truncated_log_is_rejected() {
+        let bytes = [0; RECORD_SIZE + 5];
+        assert!(matches!(
+            read_records(&bytes[..]),
+            Err(GowsimError::Serialization(_))
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{error::Error, fmt, io};
+
+/// Everything that can go wrong reading, writing or setting up simulations
+#[derive(Debug)]
+pub enum GowsimError {
+    /// A file could not be read or written
+    Io(io::Error),
+    /// A state file, histogram or log holds data that cannot be decoded
+    Serialization(String),
+    /// A deck that cannot be played, such as one holding a card twice
+    InvalidDeck(String),
+    /// Rules or options that make no sense, such as wars burying no cards
+    InvalidConfig(String),
+}
+
+impl fmt::Display for GowsimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GowsimError::Io(error) => write!(f, "{}", error),
+            GowsimError::Serialization(message) => write!(f, "{}", message),
+            GowsimError::InvalidDeck(message) => write!(f, "Invalid deck: {}", message),
+            GowsimError::InvalidConfig(message) => write!(f, "Invalid configuration: {}", message),
+        }
+    }
+}
+
+impl Error for GowsimError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GowsimError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GowsimError {
+    fn from(error: io::Error) -> Self {
+        GowsimError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for GowsimError {
+    fn from(error: serde_json::Error) -> Self {
+        GowsimError::Serialization(error.to_string())
+    }
+}
+
+impl From<rmp_serde::decode::Error> for GowsimError {
+    fn from(error: rmp_serde::decode::Error) -> Self {
+        GowsimError::Serialization(error.to_string())
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for GowsimError {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        GowsimError::Serialization(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binary_log::read_records,
+        game::{Deck, Game, GameRules, WarLengthRule},
+        state_file::decode_state,
+    };
+
+    /// Reader failing on every read, like a file on a vanished disk
+    struct FailingReader;
+
+    impl io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk gone"))
+        }
+    }
+
+    #[test]
+    fn every_variant_is_produced_by_its_failure() {
+        let error = read_records(FailingReader).unwrap_err();
+        assert!(matches!(error, GowsimError::Io(_)));
+        assert_eq!(error.to_string(), "disk gone");
+        assert!(error.source().is_some());
+
+        let error = decode_state(&[]).unwrap_err();
+        assert!(matches!(error, GowsimError::Serialization(_)));
+        assert_eq!(error.to_string(), "Empty state file");
+        assert!(error.source().is_none());
+
+        let deck: Deck = "2H 2H".parse().unwrap();
+        let error = Game::try_from_deck(deck, 0, GameRules::default())
+            .err()
+            .unwrap();
+        assert!(matches!(error, GowsimError::InvalidDeck(_)));
+        assert!(error.to_string().starts_with("Invalid deck: "));
+
+        let rules = GameRules {
+            war_length: WarLengthRule::Fixed(0),
+            ..GameRules::default()
+        };
+        let error = rules.validate().unwrap_err();
+        assert!(matches!(error, GowsimError::InvalidConfig(_)));
+        assert!(error.to_string().starts_with("Invalid configuration: "));
+    }
+
+    #[test]
+    fn conversions_keep_the_message() {
+        let json = serde_json::from_str::<u8>("no").unwrap_err();
+        let message = json.to_string();
+        assert_eq!(GowsimError::from(json).to_string(), message);
+        let msgpack = rmp_serde::from_slice::<u8>(&[0xc1]).unwrap_err();
+        let message = msgpack.to_string();
+        assert!(matches!(
+            GowsimError::from(msgpack),
+            GowsimError::Serialization(converted) if converted == message
+        ));
+    }
+}
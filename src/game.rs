@@ -1,8 +1,23 @@
-use std::mem::swap;
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+    mem::swap,
+    ops::BitOr,
+    str::FromStr,
+};
 
-use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
+use clap::ValueEnum;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::{pcg::Pcg32, splitmix::SplitMix64, GowsimError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Suit {
     Hearts,
     Diamonds,
@@ -10,7 +25,25 @@ pub enum Suit {
     Spades,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Color of a [`Suit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Color {
+    Red,
+    Black,
+}
+
+impl Suit {
+    pub fn color(&self) -> Color {
+        match *self {
+            Suit::Hearts | Suit::Diamonds => Color::Red,
+            Suit::Clubs | Suit::Spades => Color::Black,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Face {
     Number(u8),
     Jack,
@@ -20,6 +53,17 @@ pub enum Face {
 }
 
 impl Face {
+    /// Inverse of [`Face::measure_strength`]
+    pub fn from_strength(strength: usize) -> Option<Face> {
+        match strength {
+            2..=10 => Some(Face::Number(strength as u8)),
+            11 => Some(Face::Jack),
+            12 => Some(Face::Queen),
+            13 => Some(Face::King),
+            14 => Some(Face::Ace),
+            _ => None,
+        }
+    }
     pub fn measure_strength(&self) -> usize {
         match *self {
             Face::Number(a) => a as usize,
@@ -41,7 +85,8 @@ impl Face {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Card {
     suit: Suit,
     face: Face,
@@ -51,42 +96,327 @@ impl Card {
     pub fn new(suit: Suit, face: Face) -> Self {
         Card { suit, face }
     }
+    pub fn suit(&self) -> Suit {
+        self.suit
+    }
+    pub fn face(&self) -> Face {
+        self.face
+    }
+    pub fn color(&self) -> Color {
+        self.suit.color()
+    }
+}
+
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = match *self {
+            Suit::Hearts => "H",
+            Suit::Diamonds => "D",
+            Suit::Clubs => "C",
+            Suit::Spades => "S",
+        };
+        f.write_str(letter)
+    }
+}
+
+impl fmt::Display for Face {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Face::Number(a) => write!(f, "{}", a),
+            Face::Jack => f.write_str("J"),
+            Face::Queen => f.write_str("Q"),
+            Face::King => f.write_str("K"),
+            Face::Ace => f.write_str("A"),
+        }
+    }
+}
+
+/// Text encoding of a card as face followed by suit, e.g. `10H` or `AS`
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.face, self.suit)
+    }
+}
+
+impl TryFrom<char> for Suit {
+    type Error = String;
+
+    /// Parses the letter printed by `Display`
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            'H' => Ok(Suit::Hearts),
+            'D' => Ok(Suit::Diamonds),
+            'C' => Ok(Suit::Clubs),
+            'S' => Ok(Suit::Spades),
+            c => Err(format!("expected a suit out of H, D, C or S, got `{}`", c)),
+        }
+    }
+}
+
+impl TryFrom<char> for Face {
+    type Error = String;
+
+    /// Parses a single character face, every face but 10 which needs [`Face::from_str`]
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '2'..='9' => Ok(Face::Number(c as u8 - b'0')),
+            'J' => Ok(Face::Jack),
+            'Q' => Ok(Face::Queen),
+            'K' => Ok(Face::King),
+            'A' => Ok(Face::Ace),
+            c => Err(format!(
+                "expected a face out of 2 to 10, J, Q, K or A, got `{}`",
+                c
+            )),
+        }
+    }
+}
+
+impl FromStr for Face {
+    type Err = String;
+
+    /// Parses the face printed by `Display`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            _ if s == "10" => Ok(Face::Number(10)),
+            (Some(c), None) => Face::try_from(c),
+            _ => Err(format!(
+                "expected a face out of 2 to 10, J, Q, K or A, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for Card {
+    type Err = String;
+
+    /// Parses the encoding printed by `Display`, e.g. `10H` or `AS`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let suit = chars
+            .next_back()
+            .ok_or_else(|| "expected a card, got an empty string".to_string())?;
+        Ok(Card::new(Suit::try_from(suit)?, chars.as_str().parse()?))
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Player {
     pub draw_pile: Vec<Card>,
     pub winnings_pile: Vec<Card>,
+    /// Number of times the winnings pile was turned over to become the draw pile
+    pub recycle_count: usize,
 }
 
 impl Player {
     pub fn is_dead(&self) -> bool {
         self.count_cards() == 0
     }
-    #[allow(unused)]
-    pub fn is_winner(&self) -> bool {
-        self.count_cards() == 52
+    /// Whether the player holds all `total_cards` of the game, see [`Game::total_cards`]
+    pub fn is_winner(&self, total_cards: usize) -> bool {
+        self.count_cards() == total_cards
     }
     pub fn count_cards(&self) -> usize {
         self.draw_pile.len() + self.winnings_pile.len()
     }
+    /// Sum of the face strengths of all cards held
+    ///
+    /// Every card adds at most 14, so the sum fits into a `usize` for piles of up to
+    /// `usize::MAX / 14` cards, 4681 even on 16 bit targets. Larger piles panic rather than
+    /// wrap around.
     pub fn measure_strength(&self) -> usize {
+        self.measure_strength_with(standard_strength)
+    }
+    /// Same as [`Player::measure_strength`], but every card adds its `strength`, such as
+    /// [`GameRules::strength`]
+    pub fn measure_strength_with(&self, strength: fn(&Card) -> usize) -> usize {
         self.draw_pile
             .iter()
             .chain(self.winnings_pile.iter())
-            .map(|card| card.face.measure_strength())
-            .sum()
+            .map(strength)
+            .try_fold(0usize, usize::checked_add)
+            .expect("Pile strength fits into usize")
     }
-    pub fn draw(&mut self) -> Option<Card> {
-        if self.draw_pile.len() == 0 && self.winnings_pile.len() != 0 {
+    /// Turns the winnings pile over to become the draw pile once the draw pile ran out,
+    /// returning the number of cards it then holds
+    pub fn recycle(&mut self) -> Option<usize> {
+        if self.draw_pile.is_empty() && !self.winnings_pile.is_empty() {
             swap(&mut self.draw_pile, &mut self.winnings_pile);
+            self.recycle_count += 1;
+            Some(self.draw_pile.len())
+        } else {
+            None
         }
+    }
+    /// Takes the top card of the draw pile, recycling the winnings pile first if needed
+    pub fn draw(&mut self) -> Option<Card> {
+        self.recycle();
         self.draw_pile.pop()
     }
 }
 
-pub fn create_standard_deck() -> Vec<Card> {
-    let mut deck: Vec<Card> = Vec::with_capacity(52);
+/// Text encoding of a sequence of cards, separated by spaces
+pub fn format_cards(cards: &[Card]) -> String {
+    cards
+        .iter()
+        .map(|card| card.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Exchanges the top card of `player` for a card matching `wanted`, taken from further down
+/// their draw pile or else from `other`'s, except for the top card of `other` if
+/// `keep_other_top`
+///
+/// Nothing changes if no such card is found.
+fn force_top_card(
+    player: &mut Player,
+    other: &mut Player,
+    wanted: impl Fn(&Card) -> bool,
+    keep_other_top: bool,
+) {
+    let Some(top) = player.draw_pile.len().checked_sub(1) else {
+        return;
+    };
+    if wanted(&player.draw_pile[top]) {
+        return;
+    }
+    if let Some(i) = player.draw_pile.iter().position(&wanted) {
+        player.draw_pile.swap(i, top);
+        return;
+    }
+    let searched = other.draw_pile.len() - usize::from(keep_other_top);
+    if let Some(i) = other.draw_pile[..searched].iter().position(&wanted) {
+        swap(&mut player.draw_pile[top], &mut other.draw_pile[i]);
+    }
+}
+
+/// Number of pairs of cards in the wrong order compared to [`create_standard_deck`]
+///
+/// A sorted deck scores 0 and a reversed one the maximum of `n * (n - 1) / 2` for `n`
+/// distinct cards. Identical cards of multiple decks are never out of order with each other.
+pub fn deck_disorder(deck: &[Card]) -> usize {
+    let positions: Vec<usize> = deck.iter().map(standard_position).collect();
+    positions
+        .iter()
+        .enumerate()
+        .map(|(i, a)| positions[i + 1..].iter().filter(|b| a > b).count())
+        .sum()
+}
+
+/// Index of `card` in [`create_standard_deck`]
+fn standard_position(card: &Card) -> usize {
+    let suit = match card.suit {
+        Suit::Clubs => 0,
+        Suit::Diamonds => 1,
+        Suit::Hearts => 2,
+        Suit::Spades => 3,
+    };
+    let face = match card.face {
+        Face::Ace => 0,
+        Face::Number(n) => n as usize - 1,
+        Face::Jack => 10,
+        Face::Queen => 11,
+        Face::King => 12,
+    };
+    suit * 13 + face
+}
+
+/// Cards in the order they are dealt, alternating between player 0 and player 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Deck(Vec<Card>);
+
+impl Deck {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn cards(&self) -> &[Card] {
+        &self.0
+    }
+    pub fn into_cards(self) -> Vec<Card> {
+        self.0
+    }
+    pub fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.shuffle_with(&FisherYates, rng);
+    }
+    pub fn shuffle_with(&mut self, shuffle: &impl Shuffle, rng: &mut impl Rng) {
+        shuffle.shuffle(&mut self.0, rng);
+    }
+    /// Whether the deck holds every card of [`create_standard_deck`] exactly once
+    pub fn is_standard(&self) -> bool {
+        self.len() == STANDARD_DECK_SIZE && self.is_valid_multiset()
+    }
+    /// Cards held more than once, each listed once in the order their first copy appears
+    pub fn find_duplicates(&self) -> Vec<Card> {
+        let mut counts: HashMap<&Card, usize> = HashMap::new();
+        for card in &self.0 {
+            *counts.entry(card).or_insert(0) += 1;
+        }
+        let mut duplicates = Vec::new();
+        for card in &self.0 {
+            if counts.get(card).is_some_and(|count| *count > 1) {
+                counts.remove(card);
+                duplicates.push(card.clone());
+            }
+        }
+        duplicates
+    }
+    /// Whether the deck is made of one or more complete standard decks, every card of them
+    /// appearing equally often and no other card appearing at all
+    pub fn is_valid_multiset(&self) -> bool {
+        let mut counts: HashMap<&Card, usize> = HashMap::new();
+        for card in &self.0 {
+            *counts.entry(card).or_insert(0) += 1;
+        }
+        let standard = create_standard_deck();
+        let copies = counts.get(&standard.0[0]).copied().unwrap_or(0);
+        copies > 0
+            && counts.len() == standard.len()
+            && standard
+                .cards()
+                .iter()
+                .all(|card| counts.get(card) == Some(&copies))
+    }
+}
+
+impl From<Vec<Card>> for Deck {
+    fn from(cards: Vec<Card>) -> Self {
+        Deck(cards)
+    }
+}
+
+/// Space separated cards, see [`format_cards`]
+impl fmt::Display for Deck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_cards(&self.0))
+    }
+}
+
+impl FromStr for Deck {
+    type Err = String;
+
+    /// Parses whitespace separated cards as printed by `Display`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Card>, _>>()
+            .map(Deck)
+    }
+}
+
+/// Number of cards of [`create_standard_deck`], games dealt from other decks are played with
+/// [`Game::total_cards`] instead
+pub const STANDARD_DECK_SIZE: usize = 52;
+
+pub fn create_standard_deck() -> Deck {
+    let mut deck: Vec<Card> = Vec::with_capacity(STANDARD_DECK_SIZE);
 
     for suit in [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades] {
         for face in [
@@ -108,16 +438,128 @@ pub fn create_standard_deck() -> Vec<Card> {
         }
     }
 
+    Deck(deck)
+}
+
+/// Deck shuffled by an algorithm that does not depend on the `rand` crate
+///
+/// Starting from [`create_standard_deck`], a Fisher–Yates shuffle driven by
+/// [`SplitMix64`] seeded with `seed` swaps, for `i` from 51 down to 1, the card at `i` with the
+/// card at `next_u64() % (i + 1)`. Ports to other languages reproduce the exact same decks.
+pub fn create_deck_from_seed(seed: u64) -> Deck {
+    let mut deck = create_standard_deck();
+    let mut rng = SplitMix64::new(seed);
+    for i in (1..deck.len()).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        deck.0.swap(i, j);
+    }
     deck
 }
 
-pub fn create_shuffled_deck(rng: &mut ThreadRng) -> Vec<Card> {
+pub fn create_shuffled_deck(rng: &mut impl Rng) -> Deck {
+    create_shuffled_deck_with(&FisherYates, rng)
+}
+
+/// Standard deck put in order by `shuffle`
+pub fn create_shuffled_deck_with(shuffle: &impl Shuffle, rng: &mut impl Rng) -> Deck {
     let mut deck = create_standard_deck();
-    deck.shuffle(rng);
+    deck.shuffle_with(shuffle, rng);
     deck
 }
 
+/// A way of shuffling cards, drawing its randomness from `rng`
+pub trait Shuffle {
+    fn shuffle(&self, cards: &mut [Card], rng: &mut impl Rng);
+}
+
+/// Uniformly random permutation, the shuffle used unless another one is chosen
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FisherYates;
+
+impl Shuffle for FisherYates {
+    fn shuffle(&self, cards: &mut [Card], rng: &mut impl Rng) {
+        cards.shuffle(rng);
+    }
+}
+
+/// Repeated riffle shuffles following the Gilbert–Shannon–Reeds model of shuffling by hand
+///
+/// Every riffle cuts the cards at a binomially distributed position and interleaves both
+/// halves, dropping the next card from a half with a probability proportional to its size.
+/// About seven riffles of a 52 card deck come close to a uniformly random order, fewer leave
+/// runs of the previous order intact.
+#[derive(Debug, Clone, Copy)]
+pub struct RiffleShuffle {
+    pub riffles: usize,
+}
+
+impl Shuffle for RiffleShuffle {
+    fn shuffle(&self, cards: &mut [Card], rng: &mut impl Rng) {
+        let mut riffled = Vec::with_capacity(cards.len());
+        for _ in 0..self.riffles {
+            let cut = (0..cards.len()).filter(|_| rng.gen_bool(0.5)).count();
+            let (mut left, mut right) = cards.split_at(cut);
+            while !left.is_empty() || !right.is_empty() {
+                let from_left = rng.gen_range(0..left.len() + right.len()) < left.len();
+                let half = if from_left { &mut left } else { &mut right };
+                riffled.push(half[0].clone());
+                *half = &half[1..];
+            }
+            cards.clone_from_slice(&riffled);
+            riffled.clear();
+        }
+    }
+}
+
+/// Names of player 0 and player 1 used when displaying events and games
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerNames([String; 2]);
+
+impl PlayerNames {
+    pub fn new(player0: String, player1: String) -> Self {
+        PlayerNames([player0, player1])
+    }
+    pub fn name(&self, player_id: usize) -> &str {
+        &self.0[player_id]
+    }
+}
+
+/// `Player 0` and `Player 1`
+impl Default for PlayerNames {
+    fn default() -> Self {
+        PlayerNames::new("Player 0".to_string(), "Player 1".to_string())
+    }
+}
+
+/// Comma separated names, as parsed by `FromStr`
+impl fmt::Display for PlayerNames {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.0[0], self.0[1])
+    }
+}
+
+impl FromStr for PlayerNames {
+    type Err = String;
+
+    /// Parses two comma separated names, e.g. `Alice,Bob`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(',').map(str::trim).collect::<Vec<_>>()[..] {
+            [player0, player1] if !player0.is_empty() && !player1.is_empty() => {
+                Ok(PlayerNames::new(player0.to_string(), player1.to_string()))
+            }
+            _ => Err(format!("expected two comma separated names, got `{}`", s)),
+        }
+    }
+}
+
+/// Displays the wrapped value with the players called by their [`PlayerNames`]
+pub struct Named<'a, T> {
+    value: &'a T,
+    names: &'a PlayerNames,
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Event {
     GameOver {
         winning_player_id: usize,
@@ -140,203 +582,2457 @@ pub enum Event {
     WarEnd {
         winning_player_id: usize,
         final_top_cards: (Card, Card),
+        /// Number of cards transferred to the winner, including those of nested wars
+        pot_size: usize,
+    },
+    /// The winnings pile was turned over to become the draw pile, see [`Player::recycle`]
+    PileRecycled {
+        player_id: usize,
+        cards_in_pile: usize,
     },
 }
 
-#[derive(Debug, Clone)]
-pub struct Stats {
-    pub turn_number: usize,
+/// How a war is decided when the last buried cards tie again while both players have cards left
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WarTie {
+    /// Another war is fought on top of the pot
+    #[default]
+    NestedWar,
+    /// The player with the stronger pile under [`GameRules::strength`] wins, weaker with
+    /// [`GameRules::inverted`] and a coin flip if the piles are equally strong
+    PileStrength,
+    /// A coin flip decides
+    CoinFlip,
 }
 
-#[derive(Debug, Clone)]
-pub struct Game {
-    pub players: (Player, Player),
-    pub stats: Stats,
-    rng: ThreadRng,
+/// What happens to the cards left over when a deck does not divide evenly between the
+/// players
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RemainderPolicy {
+    /// The left over cards are dealt one each to the first players, so player 0 receives one
+    /// card more than player 1 from an odd deck
+    #[default]
+    DealToFirst,
+    /// The left over cards at the bottom of the deck are not dealt and take no part in the game
+    Discard,
 }
 
-impl Game {
-    pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        let deck = create_shuffled_deck(&mut rng);
-        let mut player0 = Player {
-            draw_pile: Vec::new(),
-            winnings_pile: Vec::new(),
-        };
-        let mut player1 = Player {
-            draw_pile: Vec::new(),
-            winnings_pile: Vec::new(),
-        };
-        for (num, card) in deck.into_iter().enumerate() {
-            match (num, card) {
-                (a, b) if a % 2 == 0 => player0.draw_pile.push(b),
-                (_, b) => player1.draw_pile.push(b),
-            }
-        }
-        Game {
-            players: (player0, player1),
-            stats: Stats { turn_number: 0 },
-            rng,
+/// Order in which the cards of a won pot are put onto the winner's winnings pile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PotShuffle {
+    /// The pot is shuffled, consuming randomness from the game's RNG
+    #[default]
+    Random,
+    /// The winner's cards are put down before the loser's, no randomness is consumed
+    WinnerFirst,
+}
+
+/// Number of cards each player buries face down when a war starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WarLengthRule {
+    /// The same number of cards for every war, at least 1
+    Fixed(usize),
+    /// The strength of the tied cards, from 2 for twos up to 14 for aces
+    ByStrength,
+    /// The table of [`Face::war_length`], where aces bury a single card and kings 14
+    #[default]
+    ByWarLengthTable,
+}
+
+impl FromStr for WarLengthRule {
+    type Err = String;
+
+    /// Parses `strength`, `table` or a fixed number of cards
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strength" => Ok(WarLengthRule::ByStrength),
+            "table" => Ok(WarLengthRule::ByWarLengthTable),
+            n => match n.parse() {
+                Ok(0) => Err("wars have to bury at least one card".to_string()),
+                Ok(n) => Ok(WarLengthRule::Fixed(n)),
+                Err(_) => Err(format!(
+                    "expected `strength`, `table` or a number of cards, got `{}`",
+                    n
+                )),
+            },
         }
     }
-    #[allow(unused)]
-    pub fn short_print(&self) -> String {
-        format!(
-            "Game{{ round {} [{}:{} cards, {} total, valued {}], [{}:{} cards, {} total, valued {}] }}",
-            self.stats.turn_number,
-            self.players.0.draw_pile.len(),
-            self.players.0.winnings_pile.len(),
-            self.players.0.count_cards(),
-            self.players.0.measure_strength(),
-            self.players.1.draw_pile.len(),
-            self.players.1.winnings_pile.len(),
-            self.players.1.count_cards(),
-            self.players.1.measure_strength(),
-        )
+}
+
+/// Number of cards buried by each player in a war started by tied cards of `face`
+pub fn war_length(face: &Face, rules: &GameRules) -> usize {
+    match rules.war_length {
+        WarLengthRule::Fixed(n) => n,
+        WarLengthRule::ByStrength => face.measure_strength(),
+        WarLengthRule::ByWarLengthTable => face.war_length(),
     }
-    pub fn step(&mut self) -> Option<Vec<Event>> {
-        if self.players.0.is_dead() || self.players.1.is_dead() {
-            // Game is over, nothing is going to happen (win event is emitted after the last turn)
-            return None;
-        }
-        self.stats.turn_number += 1;
+}
 
-        let mut events = Vec::new();
+/// Variations on the standard rules of the game
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameRules {
+    /// The lower card wins the pot (inverted War)
+    pub inverted: bool,
+    pub pot_shuffle: PotShuffle,
+    /// Number of cards moved from player 1 to player 0 after dealing
+    pub handicap: usize,
+    pub war_length: WarLengthRule,
+    /// The tied cards starting a war are won with the pot, otherwise each player puts their
+    /// own tied card on their winnings pile when the war ends, unless the war left the loser
+    /// without cards
+    pub include_trigger_cards: bool,
+    pub war_tie: WarTie,
+    /// Number of cards taken from the top of the deck before dealing, out of play for the whole
+    /// game
+    pub burn_count: usize,
+    pub remainder: RemainderPolicy,
+    /// Number of cards handed to a player at once while dealing, 1 alternates single cards
+    pub deal_chunk: usize,
+    /// After dealing, the top card of player 0 is exchanged for a card of this face and the one
+    /// of player 1 for a card tying with it, so the first battle starts a war
+    pub first_war: Option<Face>,
+    /// A battle between equally strong cards of different colors is won by the card of this
+    /// color instead of starting a war
+    pub color_tiebreak: Option<Color>,
+    /// Generator of the deck shuffle and of all randomness during play of a seeded game
+    pub rng: RngAlgorithm,
+    /// Strength of a card in battles, the stronger card wins, by default [`standard_strength`]
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_strength"))]
+    pub strength: fn(&Card) -> usize,
+}
 
-        match (self.players.0.draw(), self.players.1.draw()) {
-            (Some(a), Some(b)) if a.face != b.face => {
-                let mut pot = vec![a.clone(), b.clone()];
-                pot.shuffle(&mut self.rng);
-                match a.face.measure_strength().cmp(&b.face.measure_strength()) {
-                    std::cmp::Ordering::Less => {
-                        self.players.1.winnings_pile.extend(pot.clone());
-                        events.push(Event::ShortBattle {
-                            winning_player_id: 1,
-                            winning_card: b,
-                            losing_card: a,
-                            pot,
-                        });
-                    }
-                    std::cmp::Ordering::Greater => {
-                        self.players.0.winnings_pile.extend(pot.clone());
-                        events.push(Event::ShortBattle {
-                            winning_player_id: 0,
-                            winning_card: a,
-                            losing_card: b,
-                            pot,
-                        });
-                    }
-                    std::cmp::Ordering::Equal => {
-                        unreachable!("Covered by war match branch")
-                    }
-                }
-            }
-            (_, None) | (None, _) => {
-                // Will die in the checks after this match, likely unreachable
-            }
-            (Some(a), Some(b)) => {
-                let mut pot = (vec![a.clone()], vec![b.clone()]);
-                let mut war_events = Vec::new();
-                resolve_war(self, &mut pot, &mut war_events);
-                events.extend(war_events);
-            }
+impl GameRules {
+    /// Checks for rules the game cannot be played with, which would otherwise panic or never
+    /// end
+    pub fn validate(&self) -> Result<(), GowsimError> {
+        if self.deal_chunk == 0 {
+            return Err(GowsimError::InvalidConfig(
+                "cards are dealt in chunks of at least 1".to_string(),
+            ));
         }
-
-        if self.players.0.is_dead() {
-            events.push(Event::GameOver {
-                winning_player_id: 1,
-            })
+        if self.war_length == WarLengthRule::Fixed(0) {
+            return Err(GowsimError::InvalidConfig(
+                "wars bury at least one card".to_string(),
+            ));
         }
-        if self.players.1.is_dead() {
-            events.push(Event::GameOver {
-                winning_player_id: 0,
-            })
+        Ok(())
+    }
+}
+
+/// Pseudo random number generator of a seeded game, see [`GameRules::rng`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RngAlgorithm {
+    /// [`StdRng`] of `rand` 0.8, ChaCha with 12 rounds, which recorded seeds are tied to
+    #[default]
+    Std,
+    /// ChaCha with 8 rounds
+    #[value(name = "chacha8")]
+    ChaCha8,
+    /// [`Pcg32`]
+    Pcg,
+    /// [`SplitMix64`]
+    #[value(name = "splitmix")]
+    SplitMix,
+}
+
+/// Strength of the card's face, see [`Face::measure_strength`]
+pub fn standard_strength(card: &Card) -> usize {
+    card.face.measure_strength()
+}
+
+#[cfg(feature = "serde")]
+fn default_strength() -> fn(&Card) -> usize {
+    standard_strength
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            inverted: false,
+            pot_shuffle: PotShuffle::default(),
+            handicap: 0,
+            war_length: WarLengthRule::default(),
+            include_trigger_cards: true,
+            war_tie: WarTie::default(),
+            burn_count: 0,
+            remainder: RemainderPolicy::default(),
+            deal_chunk: 1,
+            first_war: None,
+            color_tiebreak: None,
+            rng: RngAlgorithm::default(),
+            strength: standard_strength,
         }
+    }
+}
+
+/// Set of [`Event`] variants, combined with `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventKind(u8);
 
-        Some(events)
+impl EventKind {
+    pub const GAME_OVER: EventKind = EventKind(1);
+    pub const SHORT_BATTLE: EventKind = EventKind(1 << 1);
+    pub const WAR_START: EventKind = EventKind(1 << 2);
+    pub const WAR_SHORTENED: EventKind = EventKind(1 << 3);
+    pub const WAR_END: EventKind = EventKind(1 << 4);
+    pub const PILE_RECYCLED: EventKind = EventKind(1 << 5);
+    pub const ALL: EventKind = EventKind(0b11_1111);
+    pub const NONE: EventKind = EventKind(0);
+    pub fn contains(&self, other: EventKind) -> bool {
+        self.0 & other.0 == other.0
     }
 }
 
-fn resolve_war(game: &mut Game, pot: &mut (Vec<Card>, Vec<Card>), events: &mut Vec<Event>) {
-    let top_at_start = (pot.0.last().unwrap().clone(), pot.1.last().unwrap().clone());
-    if top_at_start.0.face != top_at_start.1.face {
-        panic!("Cards cannot start a war");
+impl BitOr for EventKind {
+    type Output = EventKind;
+    fn bitor(self, rhs: EventKind) -> EventKind {
+        EventKind(self.0 | rhs.0)
     }
-    let expected_length = top_at_start.0.face.war_length();
-    events.push(Event::WarStart {
-        top_cards: top_at_start,
-        expected_length,
-    });
+}
 
-    for i in 1..=expected_length {
-        if game.players.0.count_cards() != 0 && game.players.1.count_cards() != 0 {
-            if let (Some(a), Some(b)) = (game.players.0.draw(), game.players.1.draw()) {
-                pot.0.push(a);
-                pot.1.push(b);
-            } else {
-                unreachable!("Checked players have at least one card before drawing. Drawing a card now should never fail");
+impl Event {
+    pub fn named<'a>(&'a self, names: &'a PlayerNames) -> Named<'a, Event> {
+        Named { value: self, names }
+    }
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::GameOver { .. } => EventKind::GAME_OVER,
+            Event::ShortBattle { .. } => EventKind::SHORT_BATTLE,
+            Event::WarStart { .. } => EventKind::WAR_START,
+            Event::WarShortened { .. } => EventKind::WAR_SHORTENED,
+            Event::WarEnd { .. } => EventKind::WAR_END,
+            Event::PileRecycled { .. } => EventKind::PILE_RECYCLED,
+        }
+    }
+}
+
+/// Whether a battle between `a` and `b` starts a war, [`battle_winner`] finding no winner under
+/// `rules`
+pub fn is_tie(a: &Card, b: &Card, rules: &GameRules) -> bool {
+    battle_winner(a, b, rules).is_none()
+}
+
+/// Returns the id of the player whose card wins the battle, or `None` if the cards tie and
+/// start a war
+pub fn battle_winner(a: &Card, b: &Card, rules: &GameRules) -> Option<usize> {
+    let ordering = (rules.strength)(a).cmp(&(rules.strength)(b));
+    let ordering = if rules.inverted {
+        ordering.reverse()
+    } else {
+        ordering
+    };
+    match ordering {
+        Ordering::Greater => Some(0),
+        Ordering::Less => Some(1),
+        Ordering::Equal => match rules.color_tiebreak {
+            Some(color) if a.color() != b.color() => Some(if a.color() == color { 0 } else { 1 }),
+            _ => None,
+        },
+    }
+}
+
+impl fmt::Display for Named<'_, Event> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = self.names;
+        match self.value {
+            Event::GameOver { winning_player_id } => {
+                write!(f, "{} wins the game", names.name(*winning_player_id))
             }
-        } else {
-            events.push(Event::WarShortened {
-                player_id_with_insufficient_cards: if game.players.0.count_cards() == 0 {
-                    0
+            Event::ShortBattle {
+                winning_player_id,
+                winning_card,
+                losing_card,
+                ..
+            } => write!(
+                f,
+                "{} wins the battle with {} against {}",
+                names.name(*winning_player_id),
+                winning_card,
+                losing_card
+            ),
+            Event::WarStart {
+                top_cards,
+                expected_length,
+            } => write!(
+                f,
+                "War! {} and {} tie, both players bury {} cards",
+                top_cards.0, top_cards.1, expected_length
+            ),
+            Event::WarShortened {
+                player_id_with_insufficient_cards,
+                length_of_war_after_shortening,
+                ..
+            } => write!(
+                f,
+                "{} runs out of cards, the war is shortened to {} cards",
+                names.name(*player_id_with_insufficient_cards),
+                length_of_war_after_shortening
+            ),
+            Event::WarEnd {
+                winning_player_id,
+                final_top_cards,
+                pot_size,
+            } => {
+                let (winning_card, losing_card) = if *winning_player_id == 0 {
+                    (&final_top_cards.0, &final_top_cards.1)
                 } else {
-                    1
-                },
-                length_of_war_after_shortening: i - 1,
-                initial_length_of_war: expected_length,
-            });
-            break;
+                    (&final_top_cards.1, &final_top_cards.0)
+                };
+                write!(
+                    f,
+                    "{} wins the war with {} against {}, taking {} cards",
+                    names.name(*winning_player_id),
+                    winning_card,
+                    losing_card,
+                    pot_size
+                )
+            }
+            Event::PileRecycled {
+                player_id,
+                cards_in_pile,
+            } => write!(
+                f,
+                "{} turns over their winnings pile of {} cards",
+                names.name(*player_id),
+                cards_in_pile
+            ),
         }
     }
+}
 
-    let top_at_end = (pot.0.last().unwrap().clone(), pot.1.last().unwrap().clone());
-    let pot_cards = {
-        if game.rng.gen_bool(0.5) {
-            pot.0.iter().chain(pot.1.iter())
-        } else {
-            pot.1.iter().chain(pot.0.iter())
-        }
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.named(&PlayerNames::default()), f)
     }
-    .map(|c| c.clone());
+}
 
-    match top_at_end
-        .0
-        .face
-        .measure_strength()
-        .cmp(&top_at_end.1.face.measure_strength())
-    {
-        std::cmp::Ordering::Less => {
-            game.players.1.winnings_pile.extend(pot_cards.clone());
-            events.push(Event::WarEnd {
-                winning_player_id: 1,
-                final_top_cards: top_at_end,
-            })
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stats {
+    pub turn_number: usize,
+    /// Number of times the player holding more cards switched
+    pub lead_changes: usize,
+    /// Number of wars started by a tied battle, not counting wars nested in them
+    pub war_count: usize,
+    /// Number of war rounds, counting every nested war as another round
+    pub war_rounds: usize,
+    /// Largest number of cards held by player 0 and player 1 at the end of any turn
+    pub max_pile: (usize, usize),
+    /// Turn on which the first war started
+    pub first_war_turn: Option<usize>,
+    #[cfg(feature = "profile")]
+    pub profile: Profile,
+}
+
+impl Stats {
+    /// Combines the stats of another game into these, describing both games together
+    ///
+    /// The counters, including `turn_number`, become totals over the games, `max_pile` the
+    /// largest piles of either game and `first_war_turn` the earlier of the two first wars.
+    pub fn merge(&mut self, other: &Stats) {
+        self.turn_number += other.turn_number;
+        self.lead_changes += other.lead_changes;
+        self.war_count += other.war_count;
+        self.war_rounds += other.war_rounds;
+        self.max_pile = (
+            self.max_pile.0.max(other.max_pile.0),
+            self.max_pile.1.max(other.max_pile.1),
+        );
+        self.first_war_turn = match (self.first_war_turn, other.first_war_turn) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        #[cfg(feature = "profile")]
+        {
+            self.profile.battle_nanos += other.profile.battle_nanos;
+            self.profile.war_nanos += other.profile.war_nanos;
         }
-        std::cmp::Ordering::Greater => {
-            game.players.0.winnings_pile.extend(pot_cards.clone());
-            events.push(Event::WarEnd {
-                winning_player_id: 0,
-                final_top_cards: top_at_end,
-            })
+    }
+}
+
+/// Time spent resolving turns, split by whether they were decided by a single battle or a war
+#[cfg(feature = "profile")]
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Profile {
+    pub battle_nanos: u64,
+    pub war_nanos: u64,
+}
+
+/// Mixed into the seed of a game to obtain the seed of the RNG shuffling its deck
+const DECK_SEED_SALT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// How the deck of a seeded game is put in order before dealing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Deal {
+    /// Shuffled by an RNG derived from the seed, see [`Game::seeded`]
+    #[default]
+    Shuffled,
+    /// Shuffled by [`create_deck_from_seed`]
+    Portable,
+    /// Not shuffled at all, every game starts from [`create_standard_deck`]
+    ///
+    /// With [`PotShuffle::WinnerFirst`] and otherwise default rules nothing is random, every
+    /// game lasts 190 turns with 13 wars and is won by player 0.
+    Sorted,
+    /// Riffled this many times by [`RiffleShuffle`], starting from [`create_standard_deck`],
+    /// with the same RNG as [`Deal::Shuffled`]
+    Riffled(usize),
+}
+
+impl Deal {
+    pub fn deck(&self, seed: u64) -> Deck {
+        self.deck_with(seed, RngAlgorithm::Std)
+    }
+    /// Same as [`Deal::deck`], but shuffles with an RNG of the given algorithm
+    pub fn deck_with(&self, seed: u64, rng: RngAlgorithm) -> Deck {
+        match *self {
+            Deal::Riffled(riffles) => create_shuffled_deck_with(
+                &RiffleShuffle { riffles },
+                &mut AnyRng::seeded(rng, seed ^ DECK_SEED_SALT),
+            ),
+            Deal::Shuffled => create_shuffled_deck(&mut AnyRng::seeded(rng, seed ^ DECK_SEED_SALT)),
+            Deal::Portable => create_deck_from_seed(seed),
+            Deal::Sorted => create_standard_deck(),
         }
-        std::cmp::Ordering::Equal => {
-            if !game.players.0.is_dead() && !game.players.1.is_dead() {
-                return resolve_war(game, pot, events);
-            }
-            if game.players.0.is_dead() {
-                game.players.1.winnings_pile.extend(pot_cards.clone());
-                events.push(Event::WarEnd {
-                    winning_player_id: 1,
-                    final_top_cards: top_at_end,
-                })
-            } else {
-                game.players.0.winnings_pile.extend(pot_cards.clone());
-                events.push(Event::WarEnd {
-                    winning_player_id: 0,
-                    final_top_cards: top_at_end,
-                })
-            }
+    }
+}
+
+/// Rules deciding what happens in a turn, to play variants of War with the same game loop
+///
+/// [`Game::step_variant`] calls [`GameVariant::resolve_turn`] once per turn of a game that is
+/// not over, after counting the turn in [`Stats::turn_number`]. The game keeps the statistics
+/// all variants share, the largest piles and lead changes, and ends once a player runs out of
+/// cards.
+pub trait GameVariant {
+    /// Plays the cards of one turn, returning its events
+    fn resolve_turn(&mut self, game: &mut Game) -> Vec<Event>;
+}
+
+/// The standard rules of War, played by [`Game::step`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StandardWar;
+
+impl GameVariant for StandardWar {
+    fn resolve_turn(&mut self, game: &mut Game) -> Vec<Event> {
+        let mut events = Vec::new();
+        game.battle(&mut events, &mut |_, _| {});
+        events
+    }
+}
+
+/// A game of War between two players
+///
+/// A game created with [`Game::seeded`] is fully reproducible. Its deck is shuffled by a
+/// separate RNG derived from the seed, while all randomness during play is drawn from the
+/// game's own RNG, seeded with the seed itself. This makes [`Game::from_deck`] with the same
+/// seed replay the game given only its deal. To keep recorded seeds stable the game's RNG is
+/// consumed in this order:
+/// 1. with [`PotShuffle::Random`], one shuffle of the two card pot per short battle,
+/// 2. with [`PotShuffle::Random`], one coin flip per war when its pot is awarded,
+/// 3. with [`WarTie::CoinFlip`], or [`WarTie::PileStrength`] and equally strong piles, one coin
+///    flip per war ending in a tie, right before the coin flip awarding its pot.
+///
+/// This order is part of the reproducibility contract, changing it changes the outcome of
+/// recorded seeds and needs a new major version. The contract is tied to the default
+/// [`RngAlgorithm::Std`], both RNGs are of the algorithm of [`GameRules::rng`]. Debug builds
+/// count the values drawn, see [`Game::rng_draws`], so that a changed count for a known seed
+/// exposes such a change.
+///
+/// With the `serde` feature the RNG is not serialized, a deserialized game continues with an
+/// RNG seeded from entropy.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Game {
+    pub players: (Player, Player),
+    pub stats: Stats,
+    pub rules: GameRules,
+    #[cfg_attr(feature = "serde", serde(skip, default = "GameRng::from_entropy"))]
+    rng: GameRng,
+    /// Last player to hold more cards than the other, used to count lead changes
+    last_leader: Option<usize>,
+    /// Size of the dealt deck
+    total_cards: usize,
+    /// Pots of player 0 and player 1 in the latest war
+    last_war_pot: (Vec<Card>, Vec<Card>),
+}
+
+/// RNG of any [`RngAlgorithm`]
+#[derive(Debug, Clone)]
+enum AnyRng {
+    Std(StdRng),
+    ChaCha8(ChaCha8Rng),
+    Pcg(Pcg32),
+    SplitMix(SplitMix64),
+}
+
+impl AnyRng {
+    fn seeded(algorithm: RngAlgorithm, seed: u64) -> Self {
+        match algorithm {
+            RngAlgorithm::Std => AnyRng::Std(StdRng::seed_from_u64(seed)),
+            RngAlgorithm::ChaCha8 => AnyRng::ChaCha8(ChaCha8Rng::seed_from_u64(seed)),
+            RngAlgorithm::Pcg => AnyRng::Pcg(Pcg32::new(seed)),
+            RngAlgorithm::SplitMix => AnyRng::SplitMix(SplitMix64::new(seed)),
+        }
+    }
+
+    fn as_rng_core(&mut self) -> &mut dyn RngCore {
+        match self {
+            AnyRng::Std(rng) => rng,
+            AnyRng::ChaCha8(rng) => rng,
+            AnyRng::Pcg(rng) => rng,
+            AnyRng::SplitMix(rng) => rng,
+        }
+    }
+}
+
+impl RngCore for AnyRng {
+    fn next_u32(&mut self) -> u32 {
+        self.as_rng_core().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.as_rng_core().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.as_rng_core().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.as_rng_core().try_fill_bytes(dest)
+    }
+}
+
+/// [`AnyRng`] counting the values drawn from it in debug builds
+#[derive(Debug, Clone)]
+struct GameRng {
+    inner: AnyRng,
+    #[cfg(debug_assertions)]
+    draws: u64,
+}
+
+impl GameRng {
+    fn new(inner: AnyRng) -> Self {
+        GameRng {
+            inner,
+            #[cfg(debug_assertions)]
+            draws: 0,
+        }
+    }
+
+    fn seeded(algorithm: RngAlgorithm, seed: u64) -> Self {
+        Self::new(AnyRng::seeded(algorithm, seed))
+    }
+
+    #[cfg(feature = "serde")]
+    fn from_entropy() -> Self {
+        Self::new(AnyRng::Std(StdRng::from_entropy()))
+    }
+
+    fn count_draw(&mut self) {
+        #[cfg(debug_assertions)]
+        {
+            self.draws += 1;
+        }
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.count_draw();
+        self.inner.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.count_draw();
+        self.inner.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.count_draw();
+        self.inner.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.count_draw();
+        self.inner.try_fill_bytes(dest)
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::with_rules(GameRules::default())
+    }
+    /// Same as [`Game::with_rules`], but fails on rules that [`GameRules::validate`] rejects
+    pub fn try_with_rules(rules: GameRules) -> Result<Self, GowsimError> {
+        rules.validate()?;
+        Ok(Self::with_rules(rules))
+    }
+    pub fn with_rules(rules: GameRules) -> Self {
+        let mut rng = AnyRng::seeded(rules.rng, rand::random());
+        let deck = create_shuffled_deck(&mut rng);
+        Self::deal(deck, rules, GameRng::new(rng))
+    }
+    pub fn seeded(seed: u64, rules: GameRules) -> Self {
+        Self::dealt(seed, rules, Deal::Shuffled)
+    }
+    /// Same as [`Game::seeded`], but the deck is shuffled by [`create_deck_from_seed`]
+    ///
+    /// Combined with [`PotShuffle::WinnerFirst`] the whole game is reproducible without `rand`.
+    pub fn seeded_portable(seed: u64, rules: GameRules) -> Self {
+        Self::dealt(seed, rules, Deal::Portable)
+    }
+    /// Game with `seed` dealt from the deck chosen by `deal`
+    pub fn dealt(seed: u64, rules: GameRules, deal: Deal) -> Self {
+        Self::from_deck(deal.deck_with(seed, rules.rng), seed, rules)
+    }
+    /// Same as [`Game::dealt`], but fails on rules that [`GameRules::validate`] rejects
+    pub fn try_dealt(seed: u64, rules: GameRules, deal: Deal) -> Result<Self, GowsimError> {
+        rules.validate()?;
+        Ok(Self::dealt(seed, rules, deal))
+    }
+    /// Deals `deck` alternately to both players, starting with player 0, without shuffling it
+    ///
+    /// Cards are handed out [`GameRules::deal_chunk`] at a time. Afterwards the top
+    /// [`GameRules::handicap`] cards of player 1 are put at the bottom of player 0's pile. Decks
+    /// of odd length are dealt according to [`GameRules::remainder`].
+    pub fn from_deck(deck: Deck, seed: u64, rules: GameRules) -> Self {
+        let rng = GameRng::seeded(rules.rng, seed);
+        Self::deal(deck, rules, rng)
+    }
+    /// Same as [`Game::from_deck`], but rejects decks holding any card more than once, as
+    /// multiple decks are not standard play, and rules that [`GameRules::validate`] rejects
+    pub fn try_from_deck(deck: Deck, seed: u64, rules: GameRules) -> Result<Self, GowsimError> {
+        rules.validate()?;
+        let duplicates = deck.find_duplicates();
+        if !duplicates.is_empty() {
+            return Err(GowsimError::InvalidDeck(format!(
+                "the deck holds {} more than once",
+                format_cards(&duplicates)
+            )));
+        }
+        Ok(Self::from_deck(deck, seed, rules))
+    }
+    fn deal(deck: Deck, rules: GameRules, rng: GameRng) -> Self {
+        let empty_player = Player {
+            draw_pile: Vec::new(),
+            winnings_pile: Vec::new(),
+            recycle_count: 0,
+        };
+        let mut game = Game {
+            players: (empty_player.clone(), empty_player),
+            stats: Stats::default(),
+            rules,
+            rng,
+            last_leader: None,
+            total_cards: 0,
+            last_war_pot: (Vec::new(), Vec::new()),
+        };
+        game.redeal(deck);
+        game
+    }
+    /// Turns this game into [`Game::seeded`] with the same seed and rules, reusing the
+    /// capacity of the players' piles
+    pub fn reset(&mut self, seed: u64) {
+        self.reset_dealt(seed, Deal::Shuffled);
+    }
+    /// Same as [`Game::reset`], but turns this game into [`Game::seeded_portable`]
+    pub fn reset_portable(&mut self, seed: u64) {
+        self.reset_dealt(seed, Deal::Portable);
+    }
+    /// Same as [`Game::reset`], but turns this game into [`Game::dealt`]
+    pub fn reset_dealt(&mut self, seed: u64, deal: Deal) {
+        self.rng = GameRng::seeded(self.rules.rng, seed);
+        self.redeal(deal.deck_with(seed, self.rules.rng));
+    }
+    /// Number of values drawn from the game's RNG since it was seeded, only counted in debug
+    /// builds
+    #[cfg(debug_assertions)]
+    pub fn rng_draws(&self) -> u64 {
+        self.rng.draws
+    }
+    /// Clears all piles and statistics and deals `deck` like [`Game::from_deck`]
+    fn redeal(&mut self, deck: Deck) {
+        let mut cards = deck.into_cards();
+        cards.drain(..self.rules.burn_count.min(cards.len()));
+        if self.rules.remainder == RemainderPolicy::Discard {
+            cards.truncate(cards.len() - cards.len() % 2);
+        }
+        self.stats = Stats::default();
+        self.last_leader = None;
+        self.last_war_pot.0.clear();
+        self.last_war_pot.1.clear();
+        self.total_cards = cards.len();
+        let (player0, player1) = &mut self.players;
+        for player in [&mut *player0, &mut *player1] {
+            player.draw_pile.clear();
+            player.winnings_pile.clear();
+            player.recycle_count = 0;
+        }
+        let chunk = self.rules.deal_chunk.max(1);
+        for (num, card) in cards.into_iter().enumerate() {
+            match (num / chunk, card) {
+                (a, b) if a % 2 == 0 => player0.draw_pile.push(b),
+                (_, b) => player1.draw_pile.push(b),
+            }
+        }
+        let handicap = self.rules.handicap.min(player1.draw_pile.len());
+        let split = player1.draw_pile.len() - handicap;
+        player0.draw_pile.extend(player1.draw_pile.drain(split..));
+        player0.draw_pile.rotate_right(handicap);
+        if let Some(face) = self.rules.first_war {
+            force_top_card(player0, player1, |card| card.face == face, false);
+            if let Some(top) = player0.draw_pile.last().cloned() {
+                let ties = |card: &Card| is_tie(card, &top, &self.rules);
+                force_top_card(player1, player0, ties, true);
+            }
+        }
+    }
+    pub fn named<'a>(&'a self, names: &'a PlayerNames) -> Named<'a, Game> {
+        Named { value: self, names }
+    }
+    /// Player holding more cards than the other, `None` while both hold the same number
+    pub fn current_leader(&self) -> Option<usize> {
+        match self
+            .players
+            .0
+            .count_cards()
+            .cmp(&self.players.1.count_cards())
+        {
+            Ordering::Greater => Some(0),
+            Ordering::Less => Some(1),
+            Ordering::Equal => None,
+        }
+    }
+    /// Cards player 0 and player 1 put into the pot of the latest war, from the tied cards
+    /// starting it to the last ones drawn, including those of nested wars
+    ///
+    /// Both are empty until the first war of the game.
+    pub fn last_war_pot(&self) -> (&[Card], &[Card]) {
+        (&self.last_war_pot.0, &self.last_war_pot.1)
+    }
+    /// Number of turns played so far, same as [`Stats::turn_number`]
+    pub fn turn_number(&self) -> usize {
+        self.stats.turn_number
+    }
+    /// Number of cards in play, those of the deck the game was dealt from without the ones burned
+    /// or discarded
+    pub fn total_cards(&self) -> usize {
+        self.total_cards
+    }
+    /// Strengths of player 0 and player 1 under [`GameRules::strength`], see
+    /// [`Player::measure_strength_with`]
+    ///
+    /// Outside of a turn they add up to the strength of all cards in play.
+    pub fn strengths(&self) -> (usize, usize) {
+        (
+            self.players.0.measure_strength_with(self.rules.strength),
+            self.players.1.measure_strength_with(self.rules.strength),
+        )
+    }
+    /// Hash of the order of all cards in both players' piles
+    ///
+    /// Under rules without randomness during play, such as [`PotShuffle::WinnerFirst`], a
+    /// game reaching a state with the same hash twice is (barring hash collisions) stuck in a
+    /// loop and never ends.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for player in [&self.players.0, &self.players.1] {
+            player.draw_pile.hash(&mut hasher);
+            player.winnings_pile.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+    /// Id of the player holding all cards once the game is over
+    pub fn winner(&self) -> Option<usize> {
+        match (self.players.0.is_dead(), self.players.1.is_dead()) {
+            (false, true) => Some(0),
+            (true, false) => Some(1),
+            _ => None,
+        }
+    }
+    fn player_mut(&mut self, player_id: usize) -> &mut Player {
+        match player_id {
+            0 => &mut self.players.0,
+            1 => &mut self.players.1,
+            _ => unreachable!("Player ids are only ever 0 or 1"),
+        }
+    }
+    pub fn short_print(&self) -> String {
+        let strengths = self.strengths();
+        format!(
+            "Game{{ round {} [{}:{} cards, {} total, valued {}], [{}:{} cards, {} total, valued {}] }}",
+            self.stats.turn_number,
+            self.players.0.draw_pile.len(),
+            self.players.0.winnings_pile.len(),
+            self.players.0.count_cards(),
+            strengths.0,
+            self.players.1.draw_pile.len(),
+            self.players.1.winnings_pile.len(),
+            self.players.1.count_cards(),
+            strengths.1,
+        )
+    }
+    /// Plays turns until one of the players has run out of cards
+    pub fn play_to_completion(&mut self) {
+        while self.step().is_some() {}
+    }
+    /// Same as [`Game::play_to_completion`], but all turns share one buffer for their events
+    pub fn play_to_completion_fast(&mut self) {
+        let mut events = Vec::new();
+        while self.step_into(&mut events) {}
+    }
+    /// Plays a turn like [`Game::step`], but replaces the contents of `events` with its events
+    /// instead of allocating a new vector, returning `false` once the game is over
+    pub fn step_into(&mut self, events: &mut Vec<Event>) -> bool {
+        events.clear();
+        self.play_turn(events, &mut |_, _| {})
+    }
+    /// Plays the game to completion, calling `f` with every event in order
+    pub fn run_with(&mut self, f: impl FnMut(&Event)) {
+        self.run_with_filter(EventKind::ALL, f)
+    }
+    /// Same as [`Game::run_with`], but only calls `f` with events whose kind is in `filter`
+    pub fn run_with_filter(&mut self, filter: EventKind, mut f: impl FnMut(&Event)) {
+        while let Some(events) = self.step() {
+            events
+                .iter()
+                .filter(|event| filter.contains(event.kind()))
+                .for_each(&mut f);
+        }
+    }
+    pub fn step(&mut self) -> Option<Vec<Event>> {
+        self.step_variant(&mut StandardWar)
+    }
+    /// Plays a turn like [`Game::step`], resolving it with the rules of `variant`
+    pub fn step_variant(&mut self, variant: &mut impl GameVariant) -> Option<Vec<Event>> {
+        let mut events = Vec::new();
+        self.play_turn_with(&mut events, |game, events| {
+            events.extend(variant.resolve_turn(game))
+        })
+        .then_some(events)
+    }
+    /// Plays a turn like [`Game::step`], calling `on_war_round` with the pots of player 0 and
+    /// player 1 each time both players buried their cards in a (nested) war
+    pub fn step_observed(
+        &mut self,
+        mut on_war_round: impl FnMut(&[Card], &[Card]),
+    ) -> Option<Vec<Event>> {
+        let mut events = Vec::new();
+        self.play_turn(&mut events, &mut on_war_round)
+            .then_some(events)
+    }
+    /// Draws the top card of both players, pushing an [`Event::PileRecycled`] onto `events` for
+    /// every winnings pile turned over to do so
+    fn draw_cards(&mut self, events: &mut Vec<Event>) -> (Option<Card>, Option<Card>) {
+        for player_id in 0..2 {
+            if let Some(cards_in_pile) = self.player_mut(player_id).recycle() {
+                events.push(Event::PileRecycled {
+                    player_id,
+                    cards_in_pile,
+                });
+            }
+        }
+        (self.players.0.draw(), self.players.1.draw())
+    }
+    /// Plays a turn, pushing its events onto `events`, unless the game is over
+    fn play_turn(
+        &mut self,
+        events: &mut Vec<Event>,
+        on_war_round: &mut impl FnMut(&[Card], &[Card]),
+    ) -> bool {
+        self.play_turn_with(events, |game, events| game.battle(events, on_war_round))
+    }
+    /// Plays a turn resolved by `resolve` unless the game is over, keeping the statistics
+    /// common to all variants and pushing [`Event::GameOver`] at the end
+    fn play_turn_with(
+        &mut self,
+        events: &mut Vec<Event>,
+        resolve: impl FnOnce(&mut Self, &mut Vec<Event>),
+    ) -> bool {
+        if self.players.0.is_dead() || self.players.1.is_dead() {
+            // Game is over, nothing is going to happen (win event is emitted after the last turn)
+            return false;
+        }
+        self.stats.turn_number += 1;
+
+        resolve(self, events);
+
+        self.stats.max_pile.0 = self.stats.max_pile.0.max(self.players.0.count_cards());
+        self.stats.max_pile.1 = self.stats.max_pile.1.max(self.players.1.count_cards());
+
+        if let Some(leader) = self.current_leader() {
+            if self.last_leader.is_some_and(|last| last != leader) {
+                self.stats.lead_changes += 1;
+            }
+            self.last_leader = Some(leader);
+        }
+
+        if self.players.0.is_dead() {
+            events.push(Event::GameOver {
+                winning_player_id: 1,
+            })
+        }
+        if self.players.1.is_dead() {
+            events.push(Event::GameOver {
+                winning_player_id: 0,
+            })
+        }
+
+        true
+    }
+    /// Resolves a turn by the rules of War, a battle of the top cards followed by a war if they
+    /// tie
+    fn battle(&mut self, events: &mut Vec<Event>, on_war_round: &mut impl FnMut(&[Card], &[Card])) {
+        #[cfg(feature = "profile")]
+        let started = std::time::Instant::now();
+        match self.draw_cards(events) {
+            (Some(a), Some(b)) => match battle_winner(&a, &b, &self.rules) {
+                Some(winning_player_id) => {
+                    let (winning_card, losing_card) = if winning_player_id == 0 {
+                        (a, b)
+                    } else {
+                        (b, a)
+                    };
+                    let mut pot = vec![winning_card.clone(), losing_card.clone()];
+                    if self.rules.pot_shuffle == PotShuffle::Random {
+                        pot.shuffle(&mut self.rng);
+                    }
+                    self.player_mut(winning_player_id)
+                        .winnings_pile
+                        .extend(pot.clone());
+                    events.push(Event::ShortBattle {
+                        winning_player_id,
+                        winning_card,
+                        losing_card,
+                        pot,
+                    });
+                    #[cfg(feature = "profile")]
+                    {
+                        self.stats.profile.battle_nanos += started.elapsed().as_nanos() as u64;
+                    }
+                }
+                None => {
+                    self.stats.war_count += 1;
+                    self.stats
+                        .first_war_turn
+                        .get_or_insert(self.stats.turn_number);
+                    let mut pot = (vec![a], vec![b]);
+                    resolve_war(self, &mut pot, events, on_war_round);
+                    self.last_war_pot = pot;
+                    #[cfg(feature = "profile")]
+                    {
+                        self.stats.profile.war_nanos += started.elapsed().as_nanos() as u64;
+                    }
+                }
+            },
+            (_, None) | (None, _) => {
+                // Will die in the checks after this turn, likely unreachable
+            }
+        }
+    }
+}
+
+/// Multi line overview of the turn and both players' piles
+impl fmt::Display for Named<'_, Game> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let game = self.value;
+        match game.current_leader() {
+            Some(leader) => writeln!(
+                f,
+                "Turn {}, {} leads",
+                game.stats.turn_number,
+                self.names.name(leader)
+            )?,
+            None => writeln!(f, "Turn {}, tied", game.stats.turn_number)?,
+        }
+        for (player_id, player) in [&game.players.0, &game.players.1].into_iter().enumerate() {
+            write!(
+                f,
+                "{}: {} cards ({} to draw, {} won), strength {}",
+                self.names.name(player_id),
+                player.count_cards(),
+                player.draw_pile.len(),
+                player.winnings_pile.len(),
+                player.measure_strength(),
+            )?;
+            if let Some(top) = player.draw_pile.last() {
+                write!(f, ", next card {}", top)?;
+            }
+            if player_id == 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.named(&PlayerNames::default()), f)
+    }
+}
+
+/// Winner of a war whose last buried cards tie again, `None` if a nested war decides it
+fn stalemate_winner(game: &mut Game) -> Option<usize> {
+    if game.players.0.is_dead() {
+        return Some(1);
+    }
+    if game.players.1.is_dead() {
+        return Some(0);
+    }
+    let coin_flip = |game: &mut Game| if game.rng.gen_bool(0.5) { 0 } else { 1 };
+    match game.rules.war_tie {
+        WarTie::NestedWar => None,
+        WarTie::PileStrength => {
+            let strength = game.rules.strength;
+            let ordering = game
+                .players
+                .0
+                .measure_strength_with(strength)
+                .cmp(&game.players.1.measure_strength_with(strength));
+            let ordering = if game.rules.inverted {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            Some(match ordering {
+                Ordering::Greater => 0,
+                Ordering::Less => 1,
+                Ordering::Equal => coin_flip(game),
+            })
+        }
+        WarTie::CoinFlip => Some(coin_flip(game)),
+    }
+}
+
+/// Resolves the war started by the tied cards on top of both pots
+///
+/// Only called by [`Game::step`] once a tie was found, so the invariants on `pot` are internal
+/// and only checked in debug builds.
+fn resolve_war(
+    game: &mut Game,
+    pot: &mut (Vec<Card>, Vec<Card>),
+    events: &mut Vec<Event>,
+    on_war_round: &mut impl FnMut(&[Card], &[Card]),
+) {
+    // Every round ending in another tie starts a nested war on top of the pot, looping instead
+    // of recursing keeps arbitrarily long chains of ties off the stack
+    let (winning_player_id, top_at_end) = loop {
+        let top_at_start = (
+            pot.0.last().expect("War pots hold the tied cards").clone(),
+            pot.1.last().expect("War pots hold the tied cards").clone(),
+        );
+        debug_assert!(
+            is_tie(&top_at_start.0, &top_at_start.1, &game.rules),
+            "Cards cannot start a war"
+        );
+        game.stats.war_rounds += 1;
+        let expected_length = war_length(&top_at_start.0.face, &game.rules);
+        // Without buried cards the tied cards decide again, repeating the war forever
+        debug_assert!(expected_length >= 1, "Wars bury at least one card");
+        events.push(Event::WarStart {
+            top_cards: top_at_start,
+            expected_length,
+        });
+
+        for i in 1..=expected_length {
+            if game.players.0.count_cards() != 0 && game.players.1.count_cards() != 0 {
+                if let (Some(a), Some(b)) = game.draw_cards(events) {
+                    pot.0.push(a);
+                    pot.1.push(b);
+                } else {
+                    unreachable!("Checked players have at least one card before drawing. Drawing a card now should never fail");
+                }
+            } else {
+                events.push(Event::WarShortened {
+                    player_id_with_insufficient_cards: if game.players.0.count_cards() == 0 {
+                        0
+                    } else {
+                        1
+                    },
+                    length_of_war_after_shortening: i - 1,
+                    initial_length_of_war: expected_length,
+                });
+                break;
+            }
+        }
+        on_war_round(&pot.0, &pot.1);
+
+        let top_at_end = (
+            pot.0.last().expect("War pots hold the tied cards").clone(),
+            pot.1.last().expect("War pots hold the tied cards").clone(),
+        );
+        match battle_winner(&top_at_end.0, &top_at_end.1, &game.rules) {
+            Some(winning_player_id) => break (winning_player_id, top_at_end),
+            None => {
+                if let Some(winning_player_id) = stalemate_winner(game) {
+                    break (winning_player_id, top_at_end);
+                }
+            }
+        }
+    };
+
+    let trigger_cards = if game.rules.include_trigger_cards {
+        0
+    } else {
+        // A loser left without cards is out of the game, so their tied card goes to the winner
+        let loser_id = 1 - winning_player_id;
+        let loser_is_out = game.player_mut(loser_id).is_dead();
+        game.players.0.winnings_pile.push(pot.0[0].clone());
+        game.players.1.winnings_pile.push(pot.1[0].clone());
+        if loser_is_out {
+            let trigger_card = game.player_mut(loser_id).winnings_pile.pop();
+            game.player_mut(winning_player_id)
+                .winnings_pile
+                .extend(trigger_card);
+        }
+        1
+    };
+    let (winner_pot, loser_pot) = if winning_player_id == 0 {
+        (&pot.0[trigger_cards..], &pot.1[trigger_cards..])
+    } else {
+        (&pot.1[trigger_cards..], &pot.0[trigger_cards..])
+    };
+    let winner_first = match game.rules.pot_shuffle {
+        PotShuffle::Random => game.rng.gen_bool(0.5),
+        PotShuffle::WinnerFirst => true,
+    };
+    let pot_cards = {
+        if winner_first {
+            winner_pot.iter().chain(loser_pot.iter())
+        } else {
+            loser_pot.iter().chain(winner_pot.iter())
+        }
+    }
+    .cloned();
+    game.player_mut(winning_player_id)
+        .winnings_pile
+        .extend(pot_cards);
+    events.push(Event::WarEnd {
+        winning_player_id,
+        final_top_cards: top_at_end,
+        pot_size: winner_pot.len() + loser_pot.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(s: &str) -> Card {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn higher_card_wins_by_default() {
+        let rules = GameRules::default();
+        assert_eq!(battle_winner(&card("KH"), &card("2S"), &rules), Some(0));
+        assert_eq!(battle_winner(&card("10D"), &card("AC"), &rules), Some(1));
+        assert_eq!(battle_winner(&card("7H"), &card("7S"), &rules), None);
+    }
+
+    #[test]
+    fn lower_card_wins_inverted() {
+        let rules = GameRules {
+            inverted: true,
+            ..GameRules::default()
+        };
+        assert_eq!(battle_winner(&card("KH"), &card("2S"), &rules), Some(1));
+        assert_eq!(battle_winner(&card("10D"), &card("AC"), &rules), Some(0));
+        assert_eq!(battle_winner(&card("7H"), &card("7S"), &rules), None);
+    }
+
+    #[test]
+    fn color_tiebreak_only_decides_ties_of_different_colors() {
+        let rules = GameRules {
+            color_tiebreak: Some(Color::Red),
+            ..GameRules::default()
+        };
+        assert_eq!(battle_winner(&card("7H"), &card("7S"), &rules), Some(0));
+        assert_eq!(battle_winner(&card("7C"), &card("7D"), &rules), Some(1));
+        assert_eq!(battle_winner(&card("7C"), &card("7S"), &rules), None);
+        assert_eq!(battle_winner(&card("2H"), &card("3S"), &rules), Some(1));
+    }
+
+    #[test]
+    fn inverted_war_is_won_by_the_lower_card() {
+        let rules = GameRules {
+            inverted: true,
+            pot_shuffle: PotShuffle::WinnerFirst,
+            ..GameRules::default()
+        };
+        // The aces on top tie, a single card each is buried and the 5 beats the 6
+        let deck: Deck = "3H 9S 5C 6C AH AS".parse().unwrap();
+        let mut game = Game::from_deck(deck, 0, rules);
+        let events = game.step().unwrap();
+        assert!(matches!(
+            events.last(),
+            Some(Event::WarEnd {
+                winning_player_id: 0,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn lead_changes_match_a_trace_of_the_card_counts() {
+        let mut game = Game::seeded(7, GameRules::default());
+        let (mut leader, mut changes) = (None, 0);
+        while game.step().is_some() {
+            let counts = (game.players.0.count_cards(), game.players.1.count_cards());
+            let now = match counts.0.cmp(&counts.1) {
+                Ordering::Greater => Some(0),
+                Ordering::Less => Some(1),
+                Ordering::Equal => None,
+            };
+            if let Some(now) = now {
+                if leader.is_some_and(|leader| leader != now) {
+                    changes += 1;
+                }
+                leader = Some(now);
+            }
+        }
+        assert!(changes > 0);
+        assert_eq!(game.stats.lead_changes, changes);
+    }
+
+    #[test]
+    fn war_pot_order_is_pinned_for_a_seed() {
+        // The aces tie and player 1's buried 6 beats the 5, the coin flip decides whose pot
+        // goes down first
+        let deck: Deck = "3H 9S 5C 6C AH AS".parse().unwrap();
+        let winnings = |seed, pot_shuffle| {
+            let rules = GameRules {
+                pot_shuffle,
+                ..GameRules::default()
+            };
+            let mut game = Game::from_deck(deck.clone(), seed, rules);
+            game.step().unwrap();
+            format_cards(&game.players.1.winnings_pile)
+        };
+        assert_eq!(winnings(0, PotShuffle::Random), "AH 5C AS 6C");
+        assert_eq!(winnings(2, PotShuffle::Random), "AS 6C AH 5C");
+        for seed in 0..4 {
+            assert_eq!(winnings(seed, PotShuffle::WinnerFirst), "AS 6C AH 5C");
+        }
+    }
+
+    #[test]
+    fn misuse_of_the_public_constructors_returns_errors() {
+        let duplicate: Deck = "AS 2H AS 3C".parse().unwrap();
+        assert!(matches!(
+            Game::try_from_deck(duplicate, 0, GameRules::default()),
+            Err(GowsimError::InvalidDeck(_))
+        ));
+        let no_chunks = GameRules {
+            deal_chunk: 0,
+            ..GameRules::default()
+        };
+        assert!(matches!(
+            Game::try_with_rules(no_chunks.clone()),
+            Err(GowsimError::InvalidConfig(_))
+        ));
+        let no_burial = GameRules {
+            war_length: WarLengthRule::Fixed(0),
+            ..GameRules::default()
+        };
+        assert!(matches!(
+            Game::try_dealt(0, no_burial.clone(), Deal::Sorted),
+            Err(GowsimError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            Game::try_from_deck(create_standard_deck(), 0, no_burial),
+            Err(GowsimError::InvalidConfig(_))
+        ));
+        assert!(Game::try_from_deck(create_standard_deck(), 0, GameRules::default()).is_ok());
+    }
+
+    #[test]
+    fn war_of_a_player_without_cards_left_ends_the_game() {
+        // Both players only hold the tied cards, so the war is shortened to nothing
+        let deck: Deck = "7H 7S".parse().unwrap();
+        let mut game = Game::from_deck(deck, 0, GameRules::default());
+        let events = game.step().unwrap();
+        assert!(matches!(events[1], Event::WarShortened { .. }));
+        assert!(game.step().is_none());
+    }
+
+    #[test]
+    fn war_end_reports_the_cards_won() {
+        let pot_size = |include_trigger_cards| {
+            let rules = GameRules {
+                include_trigger_cards,
+                ..GameRules::default()
+            };
+            let deck: Deck = "3H 9S 5C 6C AH AS".parse().unwrap();
+            let mut game = Game::from_deck(deck, 0, rules);
+            match game.step().unwrap().last() {
+                Some(Event::WarEnd { pot_size, .. }) => *pot_size,
+                event => panic!("Expected the war to end, got {:?}", event),
+            }
+        };
+        // Both aces and the single buried card of each player
+        assert_eq!(pot_size(true), 4);
+        assert_eq!(pot_size(false), 2);
+    }
+
+    #[test]
+    fn war_length_follows_the_rule() {
+        let rules = |war_length| GameRules {
+            war_length,
+            ..GameRules::default()
+        };
+        let table = rules(WarLengthRule::ByWarLengthTable);
+        let strength = rules(WarLengthRule::ByStrength);
+        let fixed = rules(WarLengthRule::Fixed(3));
+        assert_eq!(war_length(&Face::Ace, &table), 1);
+        assert_eq!(war_length(&Face::King, &table), 14);
+        assert_eq!(war_length(&Face::Ace, &strength), 14);
+        assert_eq!(war_length(&Face::King, &strength), 13);
+        assert_eq!(war_length(&Face::Number(7), &strength), 7);
+        assert_eq!(war_length(&Face::Ace, &fixed), 3);
+        assert_eq!(war_length(&Face::King, &fixed), 3);
+        assert_eq!("strength".parse(), Ok(WarLengthRule::ByStrength));
+        assert_eq!("table".parse(), Ok(WarLengthRule::ByWarLengthTable));
+        assert_eq!("3".parse(), Ok(WarLengthRule::Fixed(3)));
+        assert!("0".parse::<WarLengthRule>().is_err());
+    }
+
+    #[test]
+    fn war_length_rules_give_different_length_distributions() {
+        let histogram = |war_length| {
+            crate::Simulation {
+                rules: GameRules {
+                    war_length,
+                    ..GameRules::default()
+                },
+                ..crate::Simulation::new(300, 0)
+            }
+            .run(|_| {})
+            .unwrap()
+            .histogram
+        };
+        let table = histogram(WarLengthRule::ByWarLengthTable);
+        let strength = histogram(WarLengthRule::ByStrength);
+        let fixed = histogram(WarLengthRule::Fixed(1));
+        assert_ne!(table, strength);
+        assert_ne!(table, fixed);
+        assert_ne!(strength, fixed);
+        for histogram in [table, strength, fixed] {
+            assert_eq!(histogram.values().sum::<u64>(), 300);
+        }
+    }
+
+    #[test]
+    fn recycling_needs_an_empty_draw_pile() {
+        let mut player = Player {
+            draw_pile: vec![card("2H")],
+            winnings_pile: vec![card("3S"), card("4D")],
+            recycle_count: 0,
+        };
+        assert_eq!(player.recycle(), None);
+        assert_eq!(player.draw(), Some(card("2H")));
+        assert_eq!(player.draw(), Some(card("4D")));
+        assert_eq!(player.recycle_count, 1);
+        assert_eq!(player.draw(), Some(card("3S")));
+        assert_eq!(player.draw(), None);
+        assert_eq!(player.recycle_count, 1);
+    }
+
+    #[test]
+    fn recycle_count_matches_the_recycle_events_of_a_seeded_game() {
+        let mut game = Game::seeded(2, GameRules::default());
+        let mut recycled = [0; 2];
+        game.run_with(|event| {
+            if let Event::PileRecycled { player_id, .. } = event {
+                recycled[*player_id] += 1;
+            }
+        });
+        assert!(recycled[0] > 0 && recycled[1] > 0);
+        assert_eq!(
+            recycled,
+            [game.players.0.recycle_count, game.players.1.recycle_count]
+        );
+    }
+
+    #[test]
+    fn war_rounds_are_observed_as_the_pot_grows() {
+        let rules = GameRules {
+            war_length: WarLengthRule::Fixed(1),
+            ..GameRules::default()
+        };
+        // The aces tie, then the buried fours tie again and the 9 wins the nested war
+        let deck: Deck = "9H 2S 4C 4D AH AS".parse().unwrap();
+        let mut game = Game::from_deck(deck, 0, rules);
+        let mut rounds = Vec::new();
+        game.step_observed(|pot0, pot1| rounds.push((format_cards(pot0), format_cards(pot1))))
+            .unwrap();
+        assert_eq!(
+            rounds,
+            [
+                ("AH 4C".to_string(), "AS 4D".to_string()),
+                ("AH 4C 9H".to_string(), "AS 4D 2S".to_string()),
+            ]
+        );
+        assert_eq!(game.stats.war_rounds, 2);
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn portable_decks_match_the_golden_vectors() {
+        // Generated by a Python port of the algorithm documented on create_deck_from_seed
+        let golden = [
+            (0, "8S JH 9C 2S 2D QD 6S 10C 3D AC JC 7C 9H 8C QS KH 5H 2C 3H 5C AH 6C 4S 10D 5D 8H 8D AD QH 10S QC JD 3S 4D 2H 9D 3C KD AS 7S 9S 7D JS 6H KS 4C 7H 5S 6D 4H KC 10H"),
+            (1, "7D 9C 4D 8H 8D 5S JD 6D 9S JC 5D KD 3C 2D JH 5H 7H 4H 2C AD 6S KC KH 10H 10S JS 10D 8S QC 4S 6C 7C AS 5C AH KS 6H QS QH QD 9D 3D 3S AC 4C 2H 8C 10C 3H 2S 9H 7S"),
+            (42, "7C 3H KC AS 2S AC AH KD 10D 5C 8S JH 2H 4S 6H 5D 2D 5S 6D AD 7D 2C 8H 8C 4D 7H KS 10S 4H 3D 10H JS 4C 6S 7S 3S 8D QD 9S KH 5H 6C QS 9D JD QC 3C 9H QH 9C JC 10C"),
+        ];
+        for (seed, deck) in golden {
+            assert_eq!(create_deck_from_seed(seed).to_string(), deck);
+            assert_eq!(Deal::Portable.deck(seed).to_string(), deck);
+        }
+    }
+
+    #[test]
+    fn max_pile_matches_a_trace_of_the_card_counts() {
+        let mut game = Game::seeded(4, GameRules::default());
+        let mut max_pile = (0, 0);
+        while game.step().is_some() {
+            max_pile.0 = max_pile.0.max(game.players.0.count_cards());
+            max_pile.1 = max_pile.1.max(game.players.1.count_cards());
+        }
+        assert_eq!(game.stats.max_pile, max_pile);
+        let winner_max = match game.winner() {
+            Some(0) => max_pile.0,
+            _ => max_pile.1,
+        };
+        assert_eq!(winner_max, STANDARD_DECK_SIZE);
+    }
+
+    #[test]
+    fn reset_game_is_a_fresh_seeded_game() {
+        let mut game = Game::seeded(1, GameRules::default());
+        game.play_to_completion();
+        game.reset(9);
+        assert_eq!(game.turn_number(), 0);
+        assert_eq!(game.stats.war_count, 0);
+        assert_eq!(
+            game.players.0.count_cards() + game.players.1.count_cards(),
+            52
+        );
+        assert_eq!(
+            game.players.0.recycle_count + game.players.1.recycle_count,
+            0
+        );
+        assert!(game.players.0.winnings_pile.is_empty() && game.players.1.winnings_pile.is_empty());
+        let mut fresh = Game::seeded(9, GameRules::default());
+        assert_eq!(game.players.0.draw_pile, fresh.players.0.draw_pile);
+        assert_eq!(game.players.1.draw_pile, fresh.players.1.draw_pile);
+        game.play_to_completion();
+        fresh.play_to_completion();
+        assert_eq!(game.turn_number(), fresh.turn_number());
+        assert_eq!(game.winner(), fresh.winner());
+    }
+
+    #[test]
+    fn filtered_run_only_sees_the_wanted_events() {
+        let mut game = Game::seeded(3, GameRules::default());
+        let mut war_starts = 0;
+        game.run_with_filter(EventKind::WAR_START, |event| {
+            assert!(matches!(event, Event::WarStart { .. }));
+            war_starts += 1;
+        });
+        assert!(war_starts > 0);
+        assert_eq!(war_starts, game.stats.war_rounds);
+
+        let mut game = Game::seeded(3, GameRules::default());
+        let mut kinds = Vec::new();
+        game.run_with_filter(EventKind::WAR_END | EventKind::GAME_OVER, |event| {
+            kinds.push(event.kind())
+        });
+        assert_eq!(kinds.last(), Some(&EventKind::GAME_OVER));
+        assert_eq!(kinds.len(), game.stats.war_count + 1);
+
+        let mut game = Game::seeded(3, GameRules::default());
+        game.run_with_filter(EventKind::NONE, |_| panic!("No event was wanted"));
+        assert!(game.winner().is_some());
+    }
+
+    #[test]
+    fn game_of_54_cards_is_won_by_holding_all_54() {
+        let mut cards = create_shuffled_deck(&mut StdRng::seed_from_u64(5)).into_cards();
+        cards.extend(["AS", "2H"].map(card));
+        let mut game = Game::from_deck(cards.into(), 5, GameRules::default());
+        assert_eq!(game.total_cards(), 54);
+        game.play_to_completion();
+        let (winner, loser) = match game.winner() {
+            Some(0) => (&game.players.0, &game.players.1),
+            Some(_) => (&game.players.1, &game.players.0),
+            None => panic!("The game ended without a winner"),
+        };
+        assert_eq!(winner.count_cards(), 54);
+        assert!(winner.is_winner(game.total_cards()));
+        assert!(!winner.is_winner(STANDARD_DECK_SIZE));
+        assert!(!loser.is_winner(game.total_cards()));
+    }
+
+    #[test]
+    fn suits_and_faces_parse_from_their_characters() {
+        for (c, suit) in [
+            ('S', Suit::Spades),
+            ('H', Suit::Hearts),
+            ('D', Suit::Diamonds),
+            ('C', Suit::Clubs),
+        ] {
+            assert_eq!(Suit::try_from(c), Ok(suit));
+        }
+        for n in 2..=9 {
+            assert_eq!(Face::try_from(char::from(b'0' + n)), Ok(Face::Number(n)));
+        }
+        for (c, face) in [
+            ('J', Face::Jack),
+            ('Q', Face::Queen),
+            ('K', Face::King),
+            ('A', Face::Ace),
+        ] {
+            assert_eq!(Face::try_from(c), Ok(face));
+        }
+        assert_eq!("10".parse(), Ok(Face::Number(10)));
+        for card in create_standard_deck().cards() {
+            assert_eq!(card.to_string().parse(), Ok(card.clone()));
+        }
+    }
+
+    #[test]
+    fn invalid_characters_are_rejected() {
+        assert!(Suit::try_from('s').is_err());
+        assert!(Suit::try_from('X').is_err());
+        assert!(Face::try_from('1').is_err());
+        assert!(Face::try_from('0').is_err());
+        assert!(Face::try_from('T').is_err());
+        assert!("11".parse::<Face>().is_err());
+        assert!("".parse::<Face>().is_err());
+        assert!("1H".parse::<Card>().is_err());
+        assert!("".parse::<Card>().is_err());
+    }
+
+    #[test]
+    fn standard_deck_validates() {
+        let mut deck = create_standard_deck();
+        assert_eq!(deck.len(), STANDARD_DECK_SIZE);
+        assert!(deck.is_standard());
+        assert!(deck.is_valid_multiset());
+        assert!(deck.find_duplicates().is_empty());
+        deck.shuffle(&mut StdRng::seed_from_u64(0));
+        assert_ne!(deck, create_standard_deck());
+        assert!(deck.is_standard());
+        let mut double = create_standard_deck().into_cards();
+        double.extend(deck.into_cards());
+        let double = Deck::from(double);
+        assert!(!double.is_standard());
+        assert!(double.is_valid_multiset());
+    }
+
+    #[test]
+    fn decks_with_duplicates_or_missing_cards_are_not_standard() {
+        let mut cards = create_standard_deck().into_cards();
+        cards[5] = card("KS");
+        cards[9] = card("2H");
+        let duplicates = Deck::from(cards);
+        assert_eq!(duplicates.len(), STANDARD_DECK_SIZE);
+        assert!(!duplicates.is_standard());
+        assert!(!duplicates.is_valid_multiset());
+        assert_eq!(duplicates.find_duplicates(), [card("KS"), card("2H")]);
+
+        let short: Deck = "AS 2H 3C".parse().unwrap();
+        assert_eq!(short.len(), 3);
+        assert!(!short.is_standard());
+        assert!(!short.is_valid_multiset());
+        assert!(short.find_duplicates().is_empty());
+        assert!(!Deck::from(Vec::new()).is_valid_multiset());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn game_round_trips_through_json_mid_game() {
+        let rules = GameRules {
+            pot_shuffle: PotShuffle::WinnerFirst,
+            ..GameRules::default()
+        };
+        let mut game = Game::seeded(13, rules);
+        for _ in 0..30 {
+            game.step();
+        }
+        let json = serde_json::to_string(&game).unwrap();
+        let mut restored: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        assert_eq!(restored.turn_number(), 30);
+        assert_eq!(restored.state_hash(), game.state_hash());
+        // Nothing is random with these rules, so both games also play on identically
+        game.play_to_completion();
+        restored.play_to_completion();
+        assert_eq!(restored.turn_number(), game.turn_number());
+        assert_eq!(restored.stats.war_count, game.stats.war_count);
+        assert_eq!(restored.stats.lead_changes, game.stats.lead_changes);
+        assert_eq!(restored.state_hash(), game.state_hash());
+        assert_eq!(restored.winner(), game.winner());
+    }
+
+    #[test]
+    fn player_names_are_used_when_rendering() {
+        let names: PlayerNames = "Alice, Bob".parse().unwrap();
+        assert_eq!(
+            names,
+            PlayerNames::new("Alice".to_string(), "Bob".to_string())
+        );
+        assert_eq!(names.to_string().parse(), Ok(names.clone()));
+        assert!("Alice".parse::<PlayerNames>().is_err());
+        assert!("Alice,".parse::<PlayerNames>().is_err());
+        assert!("Alice,Bob,Carol".parse::<PlayerNames>().is_err());
+
+        let event = Event::GameOver {
+            winning_player_id: 1,
+        };
+        assert_eq!(event.named(&names).to_string(), "Bob wins the game");
+        assert_eq!(event.to_string(), "Player 1 wins the game");
+        let game = Game::from_deck("2H KS".parse().unwrap(), 0, GameRules::default());
+        let rendered = game.named(&names).to_string();
+        assert!(rendered.starts_with("Turn 0, tied\nAlice: 1 cards"));
+        assert!(rendered.contains("\nBob: 1 cards"));
+    }
+
+    #[test]
+    fn sorted_deal_without_pot_shuffling_is_the_documented_fixture() {
+        let rules = GameRules {
+            pot_shuffle: PotShuffle::WinnerFirst,
+            ..GameRules::default()
+        };
+        for seed in [0, 1, 987_654] {
+            let mut game = Game::dealt(seed, rules.clone(), Deal::Sorted);
+            game.play_to_completion();
+            assert_eq!(game.turn_number(), 190);
+            assert_eq!(game.stats.war_count, 13);
+            assert_eq!(game.winner(), Some(0));
+        }
+        let histogram = crate::Simulation {
+            rules,
+            deal: Deal::Sorted,
+            ..crate::Simulation::new(10, 0)
+        }
+        .run(|_| {})
+        .unwrap()
+        .histogram;
+        assert_eq!(histogram, crate::State::from([(190, 10)]));
+    }
+
+    #[test]
+    fn first_war_turn_matches_the_first_war_start() {
+        let mut game = Game::seeded(17, GameRules::default());
+        let mut first_war = None;
+        while let Some(events) = game.step() {
+            if first_war.is_none()
+                && events
+                    .iter()
+                    .any(|event| matches!(event, Event::WarStart { .. }))
+            {
+                first_war = Some(game.turn_number());
+            }
+        }
+        assert!(first_war.is_some());
+        assert_eq!(game.stats.first_war_turn, first_war);
+
+        let mut forced = Game::seeded(
+            17,
+            GameRules {
+                first_war: Some(Face::Number(4)),
+                ..GameRules::default()
+            },
+        );
+        forced.step();
+        assert_eq!(forced.stats.first_war_turn, Some(1));
+
+        let mut peaceful = Game::from_deck("2H KS".parse().unwrap(), 0, GameRules::default());
+        peaceful.play_to_completion();
+        assert_eq!(peaceful.stats.first_war_turn, None);
+        assert_eq!(crate::Metric::FirstWarTurn.measure(&peaceful), 0);
+    }
+
+    #[test]
+    fn cards_are_conserved_with_and_without_trigger_cards() {
+        for include_trigger_cards in [true, false] {
+            let rules = GameRules {
+                include_trigger_cards,
+                ..GameRules::default()
+            };
+            for seed in 0..20 {
+                let mut game = Game::seeded(seed, rules.clone());
+                while game.step().is_some() && game.turn_number() < 5000 {
+                    let cards = game.players.0.count_cards() + game.players.1.count_cards();
+                    assert_eq!(cards, STANDARD_DECK_SIZE);
+                }
+            }
+            let deck: Deck = "3H 9S 5C 6C AH AS".parse().unwrap();
+            let mut game = Game::from_deck(deck, 0, rules);
+            game.step();
+            // Without the trigger cards in the pot each player keeps their own ace
+            let held = (game.players.0.count_cards(), game.players.1.count_cards());
+            assert_eq!(
+                held,
+                if include_trigger_cards {
+                    (1, 5)
+                } else {
+                    (2, 4)
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn war_without_trigger_cards_still_eliminates_a_player_out_of_cards() {
+        let deck: Deck = "7H 7S".parse().unwrap();
+        let rules = GameRules {
+            include_trigger_cards: false,
+            ..GameRules::default()
+        };
+        let mut game = Game::from_deck(deck, 0, rules);
+        game.step();
+        let winner = game.winner().expect("The war ends the game");
+        assert_eq!(game.player_mut(winner).count_cards(), 2);
+        assert!(game.step().is_none());
+        assert_eq!(game.turn_number(), 1);
+    }
+
+    #[test]
+    fn current_leader_holds_more_cards() {
+        let mut game = Game::from_deck("2H KS 4D 3C".parse().unwrap(), 0, GameRules::default());
+        assert_eq!(game.current_leader(), None);
+        // The 4 beats the 3
+        game.step();
+        assert_eq!(game.current_leader(), Some(0));
+        // The king beats the 2
+        game.step();
+        assert_eq!(game.current_leader(), None);
+        let odd = Game::from_deck("2H KS 4D".parse().unwrap(), 0, GameRules::default());
+        assert_eq!(odd.current_leader(), Some(0));
+        let handicapped = Game::from_deck(
+            create_standard_deck(),
+            0,
+            GameRules {
+                handicap: 1,
+                ..GameRules::default()
+            },
+        );
+        assert_eq!(handicapped.current_leader(), Some(0));
+        let mut swapped = handicapped.clone();
+        swap(&mut swapped.players.0, &mut swapped.players.1);
+        assert_eq!(swapped.current_leader(), Some(1));
+    }
+
+    #[test]
+    fn every_face_buries_at_least_one_card() {
+        for face in create_standard_deck().cards().iter().map(Card::face) {
+            assert!(face.war_length() >= 1, "{}", face);
+            for rule in [WarLengthRule::ByStrength, WarLengthRule::ByWarLengthTable] {
+                let rules = GameRules {
+                    war_length: rule,
+                    ..GameRules::default()
+                };
+                assert!(war_length(&face, &rules) >= 1, "{} {:?}", face, rule);
+            }
+        }
+    }
+
+    #[test]
+    fn single_resolve_wars_never_nest() {
+        for war_tie in [WarTie::PileStrength, WarTie::CoinFlip] {
+            let rules = GameRules {
+                war_tie,
+                war_length: WarLengthRule::Fixed(1),
+                ..GameRules::default()
+            };
+            // The buried fours tie again, which would start a nested war
+            let deck: Deck = "9H 2S 4C 4D AH AS".parse().unwrap();
+            let mut game = Game::from_deck(deck, 0, rules.clone());
+            let events = game.step().unwrap();
+            let war_starts = events
+                .iter()
+                .filter(|event| matches!(event, Event::WarStart { .. }))
+                .count();
+            assert_eq!(war_starts, 1);
+            assert_eq!(game.stats.war_rounds, 1);
+            for seed in 0..50 {
+                let mut game = Game::seeded(seed, rules.clone());
+                game.play_to_completion();
+                assert_eq!(game.stats.war_rounds, game.stats.war_count);
+            }
+        }
+    }
+
+    #[test]
+    fn pile_strength_awards_a_tied_war_to_the_stronger_pile() {
+        let rules = GameRules {
+            war_tie: WarTie::PileStrength,
+            war_length: WarLengthRule::Fixed(1),
+            ..GameRules::default()
+        };
+        // After the aces tie and the fours tie again player 1 holds the stronger king
+        let deck: Deck = "2H KS 4C 4D AH AS".parse().unwrap();
+        let mut game = Game::from_deck(deck, 0, rules);
+        let events = game.step().unwrap();
+        assert!(matches!(
+            events.last(),
+            Some(Event::WarEnd {
+                winning_player_id: 1,
+                pot_size: 4,
+                ..
+            })
+        ));
+    }
+
+    fn reversed_strength(card: &Card) -> usize {
+        15 - card.face().measure_strength()
+    }
+
+    #[test]
+    fn custom_strength_reversing_the_order_plays_like_inverted_war() {
+        let reversed = GameRules {
+            strength: reversed_strength,
+            ..GameRules::default()
+        };
+        let inverted = GameRules {
+            inverted: true,
+            ..GameRules::default()
+        };
+        assert_eq!(battle_winner(&card("2H"), &card("AS"), &reversed), Some(0));
+        assert_eq!(battle_winner(&card("KH"), &card("QS"), &reversed), Some(1));
+        assert_eq!(battle_winner(&card("5H"), &card("5S"), &reversed), None);
+        for seed in 0..20 {
+            let mut a = Game::seeded(seed, reversed.clone());
+            let mut b = Game::seeded(seed, inverted.clone());
+            a.play_to_completion();
+            b.play_to_completion();
+            assert_eq!(a.turn_number(), b.turn_number());
+            assert_eq!(a.winner(), b.winner());
+        }
+        let player = Player {
+            draw_pile: vec![card("2H"), card("AS")],
+            winnings_pile: vec![card("10D")],
+            recycle_count: 0,
+        };
+        assert_eq!(player.measure_strength(), 26);
+        assert_eq!(player.measure_strength_with(reversed_strength), 19);
+    }
+
+    #[test]
+    fn colors_follow_the_suits() {
+        assert_eq!(card("2H").color(), Color::Red);
+        assert_eq!(card("QD").color(), Color::Red);
+        assert_eq!(card("AC").color(), Color::Black);
+        assert_eq!(card("7S").color(), Color::Black);
+    }
+
+    #[test]
+    fn color_tiebreak_decides_tied_battles_and_reduces_wars() {
+        let red = GameRules {
+            color_tiebreak: Some(Color::Red),
+            ..GameRules::default()
+        };
+        assert_eq!(battle_winner(&card("7H"), &card("7S"), &red), Some(0));
+        assert_eq!(battle_winner(&card("7C"), &card("7D"), &red), Some(1));
+        assert_eq!(battle_winner(&card("7H"), &card("7D"), &red), None);
+        let war_frequency = |rules: &GameRules| {
+            let (mut wars, mut turns) = (0, 0);
+            for seed in 0..50 {
+                let mut game = Game::seeded(seed, rules.clone());
+                game.play_to_completion();
+                wars += game.stats.war_count;
+                turns += game.turn_number();
+            }
+            wars as f64 / turns as f64
+        };
+        assert!(war_frequency(&red) < war_frequency(&GameRules::default()));
+    }
+
+    #[test]
+    fn war_rounds_count_the_nested_wars_of_a_seeded_game() {
+        // Seed 8 has a war nested in another one, the only nested war of the game
+        let mut game = Game::seeded(8, GameRules::default());
+        let mut double_wars = 0;
+        let mut war_starts = 0;
+        while let Some(events) = game.step() {
+            let starts = events
+                .iter()
+                .filter(|event| matches!(event, Event::WarStart { .. }))
+                .count();
+            double_wars += usize::from(starts == 2);
+            war_starts += starts;
+        }
+        assert_eq!(double_wars, 1);
+        assert_eq!((game.stats.war_count, game.stats.war_rounds), (3, 4));
+        assert_eq!(war_starts, game.stats.war_rounds);
+    }
+
+    #[test]
+    fn odd_decks_follow_the_remainder_policy() {
+        let sizes = |game: &Game| (game.players.0.count_cards(), game.players.1.count_cards());
+        for (deck, dealt, discarded) in [
+            ("2H 3S 4D 5C 6H", (3, 2), (2, 2)),
+            ("2H 3S 4D 5C 6H 7S 8D", (4, 3), (3, 3)),
+            ("2H 3S 4D 5C", (2, 2), (2, 2)),
+        ] {
+            let deck: Deck = deck.parse().unwrap();
+            let game = Game::from_deck(deck.clone(), 0, GameRules::default());
+            assert_eq!(sizes(&game), dealt);
+            let rules = GameRules {
+                remainder: RemainderPolicy::Discard,
+                ..GameRules::default()
+            };
+            let mut game = Game::from_deck(deck, 0, rules);
+            assert_eq!(sizes(&game), discarded);
+            game.play_to_completion();
+            let (player0, player1) = sizes(&game);
+            assert_eq!(player0 + player1, discarded.0 * 2);
+        }
+        let rules = GameRules {
+            remainder: RemainderPolicy::Discard,
+            ..GameRules::default()
+        };
+        let game = Game::from_deck("2H 3S 4D 5C 6H".parse().unwrap(), 0, rules);
+        // The card at the bottom of the deck is the one left out
+        assert!(!game.players.0.draw_pile.contains(&card("6H")));
+    }
+
+    #[test]
+    fn deal_chunks_hand_out_runs_of_cards() {
+        let deck = create_standard_deck();
+        let cards = deck.cards().to_vec();
+        let dealt = |deal_chunk| {
+            let rules = GameRules {
+                deal_chunk,
+                ..GameRules::default()
+            };
+            Game::from_deck(deck.clone(), 0, rules).players
+        };
+        let (player0, player1) = dealt(1);
+        let alternating: Vec<Card> = cards.iter().step_by(2).cloned().collect();
+        assert_eq!(player0.draw_pile, alternating);
+        assert_eq!(player1.draw_pile[0], cards[1]);
+        let (player0, player1) = dealt(13);
+        assert_eq!(player0.draw_pile, [&cards[0..13], &cards[26..39]].concat());
+        assert_eq!(player1.draw_pile, [&cards[13..26], &cards[39..52]].concat());
+        // A chunk as big as the deck hands every card to player 0
+        let (player0, player1) = dealt(52);
+        assert_eq!((player0.draw_pile.len(), player1.draw_pile.len()), (52, 0));
+    }
+
+    #[test]
+    fn turn_number_counts_the_turns_played() {
+        let mut game = Game::seeded(5, GameRules::default());
+        assert_eq!(game.turn_number(), 0);
+        let mut turns = 0;
+        while game.step().is_some() {
+            turns += 1;
+            assert_eq!(game.turn_number(), turns);
+        }
+        assert_eq!(game.turn_number(), game.stats.turn_number);
+        assert!(game.winner().is_some());
+    }
+
+    #[test]
+    fn strength_of_the_largest_pile_fits_into_16_bits() {
+        let aces = |count| Player {
+            draw_pile: vec![card("AH"); count / 2],
+            winnings_pile: vec![card("AS"); count - count / 2],
+            recycle_count: 0,
+        };
+        // All aces of 8 decks
+        assert_eq!(aces(32).measure_strength(), 32 * 14);
+        let largest = usize::from(u16::MAX) / 14;
+        assert_eq!(largest, 4681);
+        let strength = aces(largest).measure_strength();
+        assert_eq!(strength, largest * 14);
+        assert!(strength <= usize::from(u16::MAX));
+        assert!(aces(largest + 1).measure_strength() > usize::from(u16::MAX));
+    }
+
+    #[test]
+    fn shuffles_keep_the_cards_of_the_deck() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let unriffled = create_shuffled_deck_with(&RiffleShuffle { riffles: 0 }, &mut rng);
+        assert_eq!(unriffled, create_standard_deck());
+        for deck in [
+            create_shuffled_deck_with(&RiffleShuffle { riffles: 3 }, &mut rng),
+            create_shuffled_deck_with(&FisherYates, &mut rng),
+        ] {
+            assert!(deck.is_standard());
+            assert_ne!(deck, create_standard_deck());
+        }
+    }
+
+    #[test]
+    fn few_riffles_give_a_different_length_distribution() {
+        let lengths = |deal: Deal, seeds: std::ops::Range<u64>| {
+            let mut histogram = crate::State::new();
+            for seed in seeds {
+                let mut game = Game::dealt(seed, GameRules::default(), deal);
+                game.play_to_completion();
+                *histogram.entry(game.turn_number()).or_default() += 1;
+            }
+            histogram
+        };
+        let reference: HashMap<usize, f64> = lengths(Deal::Shuffled, 10_000..12_000)
+            .into_iter()
+            .map(|(length, count)| (length, count as f64))
+            .collect();
+        let p_value = |deal| {
+            crate::analysis::ks_test(&lengths(deal, 0..300), &reference)
+                .unwrap()
+                .1
+        };
+        assert!(p_value(Deal::Riffled(2)) < 0.01);
+        // Seven riffles are close enough to a uniformly random order
+        assert!(p_value(Deal::Riffled(7)) > 0.05);
+    }
+
+    #[test]
+    fn sorted_decks_score_no_disorder_and_reversed_ones_the_most() {
+        let mut cards = create_standard_deck().into_cards();
+        assert_eq!(deck_disorder(&cards), 0);
+        cards.swap(10, 11);
+        assert_eq!(deck_disorder(&cards), 1);
+        cards.swap(10, 11);
+        cards.reverse();
+        assert_eq!(deck_disorder(&cards), 52 * 51 / 2);
+        let mut rng = StdRng::seed_from_u64(3);
+        let shuffled = create_shuffled_deck(&mut rng).into_cards();
+        assert!((1..52 * 51 / 2).contains(&deck_disorder(&shuffled)));
+        // Copies of a card next to each other are in order
+        let doubled: Vec<Card> = create_standard_deck()
+            .into_cards()
+            .into_iter()
+            .flat_map(|card| [card.clone(), card])
+            .collect();
+        assert_eq!(deck_disorder(&doubled), 0);
+    }
+
+    #[test]
+    fn fast_path_plays_the_same_games() {
+        let rules = GameRules {
+            war_tie: WarTie::CoinFlip,
+            ..GameRules::default()
+        };
+        for seed in 0..30 {
+            let mut game = Game::seeded(seed, rules.clone());
+            let mut fast = game.clone();
+            game.play_to_completion();
+            fast.play_to_completion_fast();
+            assert_eq!(fast.turn_number(), game.turn_number());
+            assert_eq!(fast.stats.war_rounds, game.stats.war_rounds);
+            assert_eq!(fast.winner(), game.winner());
+        }
+        // A reused buffer only ever holds the events of the last turn
+        let mut game = Game::seeded(8, GameRules::default());
+        let mut events = Vec::new();
+        while let Some(expected) = game.clone().step() {
+            assert!(game.step_into(&mut events));
+            assert_eq!(format!("{:?}", events), format!("{:?}", expected));
+        }
+        assert!(!game.step_into(&mut events));
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn forced_first_war_starts_every_game_with_a_war() {
+        let faces =
+            (2..=10)
+                .map(Face::Number)
+                .chain([Face::Jack, Face::Queen, Face::King, Face::Ace]);
+        for face in faces {
+            let rules = GameRules {
+                first_war: Some(face),
+                ..GameRules::default()
+            };
+            for seed in 0..20 {
+                let mut game = Game::seeded(seed, rules.clone());
+                let mut cards = game.players.0.draw_pile.clone();
+                cards.extend(game.players.1.draw_pile.iter().cloned());
+                assert!(Deck(cards).is_standard());
+                let events = game.step().unwrap();
+                match &events[0] {
+                    Event::WarStart { top_cards, .. } => {
+                        assert_eq!((top_cards.0.face, top_cards.1.face), (face, face))
+                    }
+                    event => panic!("{:?} starts seed {} with {:?}", face, seed, event),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn merged_stats_sum_the_counters() {
+        let played = |seed| {
+            let mut game = Game::seeded(seed, GameRules::default());
+            game.play_to_completion();
+            game.stats
+        };
+        let (first, second) = (played(8), played(14));
+        let mut stats = first.clone();
+        stats.merge(&second);
+        assert_eq!(stats.turn_number, first.turn_number + second.turn_number);
+        assert_eq!(stats.lead_changes, first.lead_changes + second.lead_changes);
+        assert_eq!(stats.war_count, 3 + 17);
+        assert_eq!(stats.war_rounds, 4 + 18);
+        assert_eq!(
+            stats.max_pile,
+            (
+                first.max_pile.0.max(second.max_pile.0),
+                first.max_pile.1.max(second.max_pile.1)
+            )
+        );
+        assert_eq!(
+            stats.first_war_turn,
+            first.first_war_turn.min(second.first_war_turn)
+        );
+        // Merging a game without wars keeps the first war
+        let merged = stats.clone();
+        stats.merge(&Stats::default());
+        assert_eq!(
+            (stats.turn_number, stats.first_war_turn),
+            (merged.turn_number, merged.first_war_turn)
+        );
+        let mut warless = Stats::default();
+        warless.merge(&merged);
+        assert_eq!(warless.first_war_turn, merged.first_war_turn);
+    }
+
+    #[test]
+    fn burned_cards_leave_fifty_to_win() {
+        let rules = GameRules {
+            burn_count: 2,
+            ..GameRules::default()
+        };
+        for seed in 0..10 {
+            let mut game = Game::seeded(seed, rules.clone());
+            let deck = Deal::Shuffled.deck(seed);
+            let burned = &deck.cards()[..2];
+            let (player0, player1) = &game.players;
+            assert_eq!((player0.count_cards(), player1.count_cards()), (25, 25));
+            assert!(
+                burned
+                    .iter()
+                    .all(|card| !player0.draw_pile.contains(card)
+                        && !player1.draw_pile.contains(card))
+            );
+            assert_eq!(game.total_cards(), 50);
+            game.play_to_completion();
+            let winner = game.winner().unwrap();
+            let players = [&game.players.0, &game.players.1];
+            assert_eq!(players[winner].count_cards(), 50);
+            assert!(players[winner].is_winner(game.total_cards()));
+        }
+    }
+
+    #[test]
+    fn duplicated_ace_of_spades_is_named_in_the_error() {
+        let mut cards = create_standard_deck().into_cards();
+        let ace_of_spades = cards.iter().position(|c| *c == card("AS")).unwrap();
+        let replaced = (ace_of_spades + 7) % cards.len();
+        cards[replaced] = card("AS");
+        let error = Game::try_from_deck(Deck::from(cards), 0, GameRules::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Invalid deck: the deck holds AS more than once"
+        );
+        assert!(Game::try_from_deck(create_standard_deck(), 0, GameRules::default()).is_ok());
+    }
+
+    #[test]
+    fn last_war_pot_holds_the_cards_of_the_latest_war() {
+        let rules = GameRules {
+            war_length: WarLengthRule::Fixed(1),
+            ..GameRules::default()
+        };
+        let deck: Deck = "9H 2S 4C 4D AH AS".parse().unwrap();
+        let mut game = Game::from_deck(deck, 0, rules);
+        assert_eq!(game.last_war_pot(), (&[][..], &[][..]));
+        game.step().unwrap();
+        let (pot0, pot1) = game.last_war_pot();
+        assert_eq!(
+            (format_cards(pot0), format_cards(pot1)),
+            ("AH 4C 9H".into(), "AS 4D 2S".into())
+        );
+
+        let mut game = Game::seeded(8, GameRules::default());
+        let mut wars = 0;
+        loop {
+            let mut rounds = Vec::new();
+            let before = (
+                game.last_war_pot().0.to_vec(),
+                game.last_war_pot().1.to_vec(),
+            );
+            if game
+                .step_observed(|pot0, pot1| rounds.push((pot0.to_vec(), pot1.to_vec())))
+                .is_none()
+            {
+                break;
+            }
+            let after = (
+                game.last_war_pot().0.to_vec(),
+                game.last_war_pot().1.to_vec(),
+            );
+            match rounds.last() {
+                // The cards drawn in the last round make up the whole pot
+                Some(last) => {
+                    wars += 1;
+                    assert_eq!(&after, last);
+                }
+                None => assert_eq!(after, before),
+            }
+        }
+        assert_eq!(wars, 3);
+        game.reset(8);
+        assert!(game.last_war_pot().0.is_empty() && game.last_war_pot().1.is_empty());
+    }
+
+    /// Tens and all faces are worth 10, like in blackjack
+    fn capped_strength(card: &Card) -> usize {
+        card.face().measure_strength().min(10)
+    }
+
+    #[test]
+    fn equally_strong_faces_tie() {
+        let rules = GameRules {
+            strength: capped_strength,
+            ..GameRules::default()
+        };
+        assert!(is_tie(&card("KH"), &card("QS"), &rules));
+        assert!(is_tie(&card("10D"), &card("AC"), &rules));
+        assert!(!is_tie(&card("9D"), &card("JC"), &rules));
+        assert!(!is_tie(&card("KH"), &card("QS"), &GameRules::default()));
+        // Player 0 plays the king and player 1 the queen first
+        let deck: Deck = "2H 3S 4D 5C KH QS".parse().unwrap();
+        let mut game = Game::from_deck(deck, 0, rules);
+        let events = game.step().unwrap();
+        match &events[0] {
+            Event::WarStart { top_cards, .. } => {
+                assert_eq!(top_cards, &(card("KH"), card("QS")))
+            }
+            event => panic!("{:?}", event),
+        }
+    }
+
+    #[test]
+    fn halves_deal_each_player_a_contiguous_half_of_the_shuffled_deck() {
+        let rules = GameRules {
+            deal_chunk: STANDARD_DECK_SIZE / 2,
+            ..GameRules::default()
+        };
+        for seed in 0..5 {
+            let game = Game::seeded(seed, rules.clone());
+            let deck = Deal::Shuffled.deck(seed).into_cards();
+            assert_eq!(game.players.0.draw_pile, deck[..26]);
+            assert_eq!(game.players.1.draw_pile, deck[26..]);
+        }
+    }
+
+    #[test]
+    fn recycle_events_report_the_turned_over_pile() {
+        let mut game = Game::seeded(2, GameRules::default());
+        let mut recycled = [0; 2];
+        loop {
+            let before = game.players.clone();
+            let Some(events) = game.step() else {
+                break;
+            };
+            for event in &events {
+                if let Event::PileRecycled {
+                    player_id,
+                    cards_in_pile,
+                } = event
+                {
+                    recycled[*player_id] += 1;
+                    assert!(*cards_in_pile > 0);
+                }
+            }
+            // Recycling before the first battle of a turn turns over the whole winnings pile
+            if let Some(Event::PileRecycled {
+                player_id,
+                cards_in_pile,
+            }) = events.first()
+            {
+                let player = [&before.0, &before.1][*player_id];
+                assert!(player.draw_pile.is_empty());
+                assert_eq!(*cards_in_pile, player.winnings_pile.len());
+            }
+        }
+        assert_eq!(recycled, [8, 4]);
+    }
+
+    #[test]
+    fn a_thousand_nested_wars_resolve_on_a_small_stack() {
+        // Pairs of equal faces from 40 decks, so every war round ties again until the pair at
+        // the bottom, where player 0's ace beats player 1's two
+        let mut cards = vec![card("AH"), card("2S")];
+        for _ in 0..40 {
+            for card in create_standard_deck().into_cards() {
+                cards.push(card.clone());
+                cards.push(card);
+            }
+        }
+        let pairs = cards.len() / 2;
+        let rules = GameRules {
+            war_length: WarLengthRule::Fixed(1),
+            ..GameRules::default()
+        };
+        let game = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || {
+                let mut game = Game::from_deck(Deck::from(cards), 0, rules);
+                game.step().unwrap();
+                game
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(game.turn_number(), 1);
+        assert_eq!(game.stats.war_count, 1);
+        assert_eq!(game.stats.war_rounds, pairs - 1);
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    /// Standard War, counting the turns it resolves
+    struct CountingWar(usize);
+
+    impl GameVariant for CountingWar {
+        fn resolve_turn(&mut self, game: &mut Game) -> Vec<Event> {
+            self.0 += 1;
+            StandardWar.resolve_turn(game)
+        }
+    }
+
+    #[test]
+    fn standard_variant_reproduces_the_seeded_game() {
+        let mut variant = Game::seeded(21, GameRules::default());
+        let mut plain = variant.clone();
+        let mut events = Vec::new();
+        let mut counting = CountingWar(0);
+        while let Some(variant_events) = variant.step_variant(&mut counting) {
+            assert!(plain.step_into(&mut events));
+            assert_eq!(format!("{:?}", variant_events), format!("{:?}", events));
+        }
+        assert!(!plain.step_into(&mut events));
+        assert_eq!(counting.0, plain.turn_number());
+        assert_eq!(variant.turn_number(), plain.turn_number());
+        assert_eq!(variant.stats.lead_changes, plain.stats.lead_changes);
+        assert_eq!(variant.stats.max_pile, plain.stats.max_pile);
+        assert_eq!(variant.winner(), plain.winner());
+    }
+
+    #[test]
+    fn strengths_add_up_to_the_strength_of_the_deck() {
+        let deck_strength: usize = create_standard_deck()
+            .cards()
+            .iter()
+            .map(standard_strength)
+            .sum();
+        assert_eq!(deck_strength, 4 * (2..=14).sum::<usize>());
+        let mut game = Game::seeded(4, GameRules::default());
+        loop {
+            let (player0, player1) = game.strengths();
+            assert_eq!(player0 + player1, deck_strength);
+            if game.step().is_none() {
+                break;
+            }
+        }
+        let winner_strength = match game.winner() {
+            Some(0) => game.strengths().0,
+            _ => game.strengths().1,
+        };
+        assert_eq!(winner_strength, deck_strength);
+        // Burned cards take their strength out of play
+        let rules = GameRules {
+            burn_count: 2,
+            ..GameRules::default()
+        };
+        let game = Game::seeded(4, rules);
+        let burned: usize = Deal::Shuffled.deck(4).cards()[..2]
+            .iter()
+            .map(standard_strength)
+            .sum();
+        let (player0, player1) = game.strengths();
+        assert_eq!(player0 + player1, deck_strength - burned);
+        // Custom strengths are summed under the rules' own function
+        let rules = GameRules {
+            strength: reversed_strength,
+            ..GameRules::default()
+        };
+        let game = Game::from_deck("2H KS".parse().unwrap(), 0, rules.clone());
+        assert_eq!(game.strengths(), (13, 2));
+        let deck_strength: usize = create_standard_deck()
+            .cards()
+            .iter()
+            .map(reversed_strength)
+            .sum();
+        let mut game = Game::seeded(4, rules);
+        loop {
+            let (player0, player1) = game.strengths();
+            assert_eq!(player0 + player1, deck_strength);
+            if game.step().is_none() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn every_constructed_deck_has_the_standard_size() {
+        assert_eq!(STANDARD_DECK_SIZE, 52);
+        let mut rng = StdRng::seed_from_u64(1);
+        for deck in [
+            create_standard_deck(),
+            create_shuffled_deck(&mut rng),
+            create_deck_from_seed(1),
+            Deal::Riffled(3).deck(1),
+        ] {
+            assert_eq!(deck.len(), STANDARD_DECK_SIZE);
+        }
+        let game = Game::seeded(1, GameRules::default());
+        assert_eq!(game.total_cards(), STANDARD_DECK_SIZE);
+        // Other decks are won by holding all of their cards instead
+        let game = Game::from_deck("2H 3S 4D 5C".parse().unwrap(), 0, GameRules::default());
+        assert_eq!(game.total_cards(), 4);
+    }
+}
+
+/// Golden RNG draw counts, a failure here means seeded games no longer replay like before,
+/// see the reproducibility contract on [`Game`]
+#[cfg(all(test, debug_assertions))]
+mod rng_draws {
+    use super::*;
+
+    #[test]
+    fn seeded_games_draw_the_pinned_number_of_values() {
+        for (seed, draws, turns) in [(0, 87, 45), (1, 209, 100), (42, 368, 188)] {
+            let mut game = Game::seeded(seed, GameRules::default());
+            assert_eq!(game.rng_draws(), 0);
+            game.play_to_completion();
+            assert_eq!((game.rng_draws(), game.turn_number()), (draws, turns));
+            game.reset(seed);
+            assert_eq!(game.rng_draws(), 0);
         }
     }
 }
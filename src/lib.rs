@@ -0,0 +1,50 @@
+//! Simulator for the card game War, collecting statistics over many games
+
+use std::collections::HashMap;
+
+pub mod analysis;
+pub mod binary_log;
+pub mod error;
+pub mod game;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod pcg;
+pub mod simulation;
+pub mod splitmix;
+pub mod state_file;
+
+pub use error::GowsimError;
+pub use simulation::{
+    simulate_collect_stats, simulate_lengths, simulate_lengths_parallel, Metric, Simulation,
+    SimulationResult,
+};
+
+/// Histogram of a per game statistic, mapping each observed value to the number of games
+///
+/// Counts are `u64` on every target, as they keep growing over resumed runs.
+pub type State = HashMap<usize, u64>;
+
+/// Adds all counts of `other` onto `state`, saturating instead of wrapping
+pub fn merge_states(state: &mut State, other: State) {
+    for (value, count) in other {
+        let total = state.entry(value).or_insert(0);
+        *total = total.saturating_add(count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_counts_grow_past_32_bits_and_saturate() {
+        let mut state = State::from([(50, u32::MAX as u64), (60, u64::MAX - 1)]);
+        merge_states(
+            &mut state,
+            State::from([(50, u32::MAX as u64), (60, 5), (70, 1)]),
+        );
+        assert_eq!(state[&50], 2 * u32::MAX as u64);
+        assert_eq!(state[&60], u64::MAX);
+        assert_eq!(state[&70], 1);
+    }
+}
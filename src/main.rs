@@ -1,91 +1,2448 @@
-use clap::Parser;
+use clap::{builder::RangedU64ValueParser, Parser, Subcommand, ValueEnum};
+use gowsim::{
+    analysis,
+    binary_log::BinaryLog,
+    game::{self, Event, Game, PlayerNames},
+    merge_states,
+    simulation::{
+        AllMetrics, Closeness, Comeback, CycleDetection, DealLog, DisorderLog, DrawnOutEndgame,
+        FinishingRank, FirstTurnWinner, JointHistogram, LongestGame, Recorder, Termination,
+        WarPotSizes, WinCount, DEFAULT_BATCH_SIZE,
+    },
+    state_file::{decode_state, encode_state, merge_dense_streaming, StateEncoding},
+    GowsimError, Metric, Simulation, SimulationResult, State,
+};
+use serde::Serialize;
 use std::{
     collections::HashMap,
-    fs::{write, File},
-    io::Read,
-    time::Instant,
+    fs::{self, write, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-mod game;
-
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(short, long)]
-    num: usize,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(short, long, required_unless_present_any = ["seed_start", "events_trace", "frames", "lead_autocorr", "limit_time"])]
+    num: Option<usize>,
+    /// Stop simulating after this many seconds, with `--num` or a seed range as an upper bound
+    /// on the number of games
+    #[arg(long, value_name = "SECS", conflicts_with = "snapshot_at")]
+    limit_time: Option<f64>,
+    /// Emit progress as JSON lines on stderr instead of human readable text
+    #[arg(long)]
+    progress_json: bool,
+    /// Overwrite a single progress line instead of printing a new one for every update
+    #[arg(long, conflicts_with = "progress_json")]
+    compact_progress: bool,
+    /// Play inverted War, where the lower card wins the pot
+    #[arg(long)]
+    inverted: bool,
+    /// Put won cards onto the winnings pile in a fixed order instead of shuffling them
+    #[arg(long)]
+    no_shuffle: bool,
+    /// Cards buried per war: `table`, `strength` or a fixed number
+    #[arg(long, default_value = "table")]
+    war_length: game::WarLengthRule,
+    /// How a war ending in another tie is decided
+    #[arg(long, value_enum, default_value_t = game::WarTie::NestedWar)]
+    war_tie: game::WarTie,
+    /// Tied battles between cards of different colors are won by this color instead of a war
+    #[arg(long, value_enum)]
+    color_tiebreak: Option<game::Color>,
+    /// Give each player their own tied card back after a war instead of adding both to the pot
+    #[arg(long)]
+    exclude_trigger_cards: bool,
+    /// Move this many cards from player 1 to player 0 after dealing
+    #[arg(long, default_value_t = 0)]
+    handicap: usize,
+    /// Deal this many cards to a player at once instead of alternating single cards
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    deal_chunk: usize,
+    /// Take this many cards out of play before dealing
+    #[arg(long, default_value_t = 0)]
+    burn: usize,
+    /// Make the first turn of every game a war by giving both players a card of this face on top
+    #[arg(long, value_name = "FACE")]
+    force_first_war: Option<game::Face>,
+    /// Seed of the first game, the following games use consecutive seeds
+    #[arg(long)]
+    seed: Option<u64>,
+    /// First seed of a range of seeds to simulate instead of `--num` games
+    #[arg(long, requires = "seed_end", conflicts_with_all = ["num", "seed"])]
+    seed_start: Option<u64>,
+    /// End of the range of seeds to simulate, exclusive
+    #[arg(long, requires = "seed_start")]
+    seed_end: Option<u64>,
+    /// Shuffle decks with the documented SplitMix64 based algorithm for ports to other languages
+    #[arg(long)]
+    portable_deal: bool,
+    /// Deal the standard deck without shuffling it, the same position every game
+    #[arg(long, conflicts_with = "portable_deal")]
+    no_shuffle_deal: bool,
+    /// Shuffle decks by riffling the standard deck this many times, like shuffling by hand
+    #[arg(long, value_name = "RIFFLES", conflicts_with_all = ["portable_deal", "no_shuffle_deal"])]
+    riffle_deal: Option<usize>,
+    /// Pseudo random number generator shuffling the decks and the pots, recorded seeds replay
+    /// the same games only with `std`
+    #[arg(long, value_enum, default_value_t = game::RngAlgorithm::Std)]
+    rng: game::RngAlgorithm,
+    /// Number of threads to simulate on, 0 uses one per core
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+    /// Play this many games, not recorded anywhere, before starting the timer
+    #[arg(long, default_value_t = 0)]
+    warmup: usize,
+    /// Number of games each thread plays between two progress updates
+    #[arg(long, default_value_t = DEFAULT_BATCH_SIZE, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    batch_size: usize,
+    /// Write state files in this format version: 0 legacy, 1 sparse or 2 dense, by default the
+    /// smaller one of 1 and 2
+    #[arg(long, value_name = "VERSION")]
+    format_version: Option<StateEncoding>,
+    /// Also save the histogram as it stood after each of these numbers of games, e.g.
+    /// `1e4,1e5`, into files named after the count
+    #[arg(long, value_name = "GAMES", value_delimiter = ',', value_parser = parse_game_count)]
+    snapshot_at: Vec<usize>,
+    /// Do not read or write the accumulated state files
+    #[arg(long)]
+    no_save: bool,
+    /// Number of decimal places of the averages and rates in the summary
+    #[arg(long, default_value_t = 1)]
+    precision: usize,
+    /// Format of the histogram written next to the state
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+    /// Separator between the columns of CSV histograms
+    #[arg(long, default_value_t = ',')]
+    delimiter: char,
+    /// Also write the empirical CDF of the accumulated histogram to `<state>_cdf.csv`
+    #[arg(long)]
+    cdf: bool,
+    /// Also write the histogram of this run to this Parquet file
+    #[cfg(feature = "parquet")]
+    #[arg(long, value_name = "PATH")]
+    parquet: Option<PathBuf>,
+    /// Write the state files into a new directory inside DIR, named after the time, seeds and
+    /// rules of the run and described by a `manifest.json`
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+    /// Collapse values seen in fewer than this many games into one `other` row of the written
+    /// histograms, the msgpack state keeps every value
+    #[arg(long, value_name = "N")]
+    tail_threshold: Option<u64>,
+    /// Leave the values below this out of the written histograms, counting them in one
+    /// `underflow` row, the msgpack state keeps every value
+    #[arg(long, value_name = "VALUE")]
+    histogram_min: Option<usize>,
+    /// Leave the values above this out of the written histograms, counting them in one
+    /// `overflow` row, the msgpack state keeps every value
+    #[arg(long, value_name = "VALUE")]
+    histogram_max: Option<usize>,
+    /// Leave out the header line of CSV histograms
+    #[arg(long)]
+    no_header: bool,
+    /// Write the histogram of this run to stdout instead of saving any state, status goes to stderr
+    #[arg(long)]
+    stdout: bool,
+    /// Per game statistic to build the histogram of
+    #[arg(long, value_enum, default_value_t = Metric::Length)]
+    metric: Metric,
+    /// Write the seed and initial deal of every game to this CSV file
+    #[arg(long, value_name = "PATH")]
+    log_deals: Option<PathBuf>,
+    /// Write the seed, disorder of the deal and length of every game to this CSV file
+    #[arg(long, value_name = "PATH")]
+    log_disorder: Option<PathBuf>,
+    /// Write the histograms of all metrics of this run to this CSV file, one
+    /// `metric,value,count` row per value
+    #[arg(long, value_name = "PATH")]
+    long_csv: Option<PathBuf>,
+    /// Write a fixed width binary record of every game to this file
+    #[arg(long, value_name = "PATH")]
+    binary_log: Option<PathBuf>,
+    /// Also build the histogram of the number of cards won by each war
+    #[arg(long)]
+    war_pot_sizes: bool,
+    /// Also build the histogram of the strength of the card winning the last battle of each game
+    #[arg(long)]
+    finishing_rank_stats: bool,
+    /// Also build the histogram of the number of cards the loser held before the last turn
+    #[arg(long)]
+    closeness_stats: bool,
+    /// Also report the geometric and harmonic mean of --metric
+    #[arg(long)]
+    means: bool,
+    /// Also report the minimum, quartiles, maximum and interquartile range of --metric
+    #[arg(long)]
+    box_plot: bool,
+    /// Print an estimate of the memory taken by the histogram
+    #[arg(long)]
+    report_memory: bool,
+    /// Report how often the winner was once behind by more than this many cards
+    #[arg(long, value_name = "X")]
+    comeback_threshold: Option<usize>,
+    /// Report how many games spend more than --endgame-turns turns with a player holding
+    /// fewer than --endgame-cards cards
+    #[arg(long)]
+    endgame_stats: bool,
+    #[arg(long, default_value_t = 4, requires = "endgame_stats")]
+    endgame_cards: usize,
+    #[arg(long, default_value_t = 20, requires = "endgame_stats")]
+    endgame_turns: usize,
+    /// Stop games after this many turns
+    #[arg(long)]
+    max_turns: Option<usize>,
+    /// Stop games returning to an earlier state and report the turn it happened at
+    #[arg(long)]
+    detect_cycle: bool,
+    /// Number of states remembered per game by --detect-cycle
+    #[arg(long, default_value_t = 1_000_000)]
+    max_cycle_states: usize,
+    /// Report how often the winner of the first turn goes on to win the game
+    #[arg(long)]
+    first_turn: bool,
+    /// Only play the game of SEED, writing the number of events of each turn as CSV to PATH
+    #[arg(long, num_args = 2, value_names = ["SEED", "PATH"])]
+    events_trace: Option<Vec<String>>,
+    /// Only play the game of SEED, printing how strongly the card count lead of player 0
+    /// correlates with itself a few turns later as CSV
+    #[arg(long, value_name = "SEED")]
+    lead_autocorr: Option<u64>,
+    /// Only play the game of SEED, writing the state after every turn as a JSON array to PATH
+    #[arg(long, num_args = 2, value_names = ["SEED", "PATH"])]
+    frames: Option<Vec<String>>,
+    /// Names of player 0 and player 1 in the output, e.g. `Alice,Bob`
+    #[arg(long, value_name = "NAMES", default_value_t)]
+    player_names: PlayerNames,
+    /// Report the time spent in battles and in wars
+    #[cfg(feature = "profile")]
+    #[arg(long)]
+    profile: bool,
+    /// Report the seed of the longest finished game
+    #[arg(long)]
+    find_longest: bool,
+    /// Write every turn of the longest game found by --find-longest to this file
+    #[arg(long, value_name = "PATH", requires = "find_longest")]
+    replay: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Csv,
+    /// An array of objects with the value and its count
+    Json,
+}
+
+impl OutputFormat {
+    fn extension(&self) -> &'static str {
+        match *self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+/// How histograms are rendered, as chosen on the command line
+struct Output {
+    format: OutputFormat,
+    delimiter: char,
+    header: bool,
+    tail_threshold: Option<u64>,
+    /// Smallest and largest value written, the ones outside are summed up
+    window: (Option<usize>, Option<usize>),
+    /// Encoding of the state files, the smaller one of sparse and dense if `None`
+    state_encoding: Option<StateEncoding>,
+    /// Directory the state files are written to
+    dir: PathBuf,
+}
+
+impl Output {
+    fn for_run(args: &Args, simulation: &Simulation) -> Self {
+        Output {
+            format: args.format,
+            delimiter: args.delimiter,
+            header: !args.no_header,
+            tail_threshold: args.tail_threshold,
+            window: (args.histogram_min, args.histogram_max),
+            state_encoding: args.format_version,
+            dir: match &args.output_dir {
+                Some(dir) => dir.join(run_name(simulation)),
+                None => PathBuf::from("."),
+            },
+        }
+    }
+
+    /// Path of the state files with `stem` inside [`Output::dir`]
+    fn stem(&self, stem: &str) -> String {
+        let stem = stem.strip_prefix("./").unwrap_or(stem);
+        self.dir.join(stem).to_string_lossy().into_owned()
+    }
+}
+
+/// Directory name unique to a run, made of the start time, the seeds and the rules
+fn run_name(simulation: &Simulation) -> String {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let rules = &simulation.rules;
+    let mut name = format!(
+        "{}-seed{}-{}games-{}",
+        started,
+        simulation.base_seed,
+        simulation.num_games,
+        simulation.metric.name()
+    );
+    match rules.war_length {
+        game::WarLengthRule::Fixed(n) => name.push_str(&format!("-war{}", n)),
+        game::WarLengthRule::ByStrength => name.push_str("-war_strength"),
+        game::WarLengthRule::ByWarLengthTable => {}
+    }
+    if rules.inverted {
+        name.push_str("-inverted");
+    }
+    if rules.pot_shuffle == game::PotShuffle::WinnerFirst {
+        name.push_str("-no_shuffle");
+    }
+    if rules.handicap > 0 {
+        name.push_str(&format!("-handicap{}", rules.handicap));
+    }
+    if rules.deal_chunk > 1 {
+        name.push_str(&format!("-chunk{}", rules.deal_chunk));
+    }
+    if rules.burn_count > 0 {
+        name.push_str(&format!("-burn{}", rules.burn_count));
+    }
+    if let Some(face) = rules.first_war {
+        name.push_str(&format!("-first_war{}", face));
+    }
+    if !rules.include_trigger_cards {
+        name.push_str("-exclude_trigger_cards");
+    }
+    if rules.war_tie != game::WarTie::NestedWar {
+        let war_tie = rules.war_tie.to_possible_value().unwrap();
+        name.push_str(&format!("-{}", war_tie.get_name()));
+    }
+    if let Some(color) = rules.color_tiebreak {
+        let color = color.to_possible_value().unwrap();
+        name.push_str(&format!("-{}_tiebreak", color.get_name()));
+    }
+    if rules.rng != game::RngAlgorithm::Std {
+        let rng = rules.rng.to_possible_value().unwrap();
+        name.push_str(&format!("-rng_{}", rng.get_name()));
+    }
+    name
+}
+
+/// Writes `manifest.json` describing the run into the output directory
+fn write_manifest(output: &Output, simulation: &Simulation) -> Result<(), GowsimError> {
+    let rules = &simulation.rules;
+    let manifest = serde_json::json!({
+        "base_seed": simulation.base_seed,
+        "num_games": simulation.num_games,
+        "metric": simulation.metric.name(),
+        "max_turns": simulation.max_turns,
+        "deal": format!("{:?}", simulation.deal),
+        "rules": {
+            "inverted": rules.inverted,
+            "pot_shuffle": format!("{:?}", rules.pot_shuffle),
+            "handicap": rules.handicap,
+            "deal_chunk": rules.deal_chunk,
+            "burn_count": rules.burn_count,
+            "first_war": rules.first_war.map(|face| face.to_string()),
+            "war_length": format!("{:?}", rules.war_length),
+            "include_trigger_cards": rules.include_trigger_cards,
+            "war_tie": format!("{:?}", rules.war_tie),
+            "color_tiebreak": rules.color_tiebreak.map(|color| format!("{:?}", color)),
+            "rng": format!("{:?}", rules.rng),
+        },
+    });
+    write_file(
+        output.dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )?;
+    Ok(())
+}
+
+/// Attaches `path` to the message of an IO error, so it tells which file failed
+fn at_path(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> GowsimError {
+    move |error| {
+        let message = format!("{}: {}", path.as_ref().display(), error);
+        GowsimError::Io(io::Error::new(error.kind(), message))
+    }
+}
+
+fn create_file(path: impl AsRef<Path>) -> Result<File, GowsimError> {
+    File::create(&path).map_err(at_path(path))
+}
+
+fn write_file(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), GowsimError> {
+    write(&path, contents).map_err(at_path(path))
+}
+
+/// Prints a status line, to stderr if the first argument is true because stdout carries data
+macro_rules! status {
+    ($to_stderr:expr, $($arg:tt)*) => {
+        if $to_stderr {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Play a single game turn by turn, pressing Enter to advance
+    Step {
+        /// Seed of the game, random if not given
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Play the game of a single seed and print its statistics
+    Inspect {
+        #[arg(long)]
+        seed: u64,
+        /// Also print every event of the game
+        #[arg(long)]
+        events: bool,
+    },
+    /// Sum state files, as written next to the CSV files, into a single one
+    Merge {
+        /// State file to write the sum to
+        #[arg(long, value_name = "PATH")]
+        output: PathBuf,
+        /// Sum dense state files without loading them into memory
+        #[arg(long)]
+        streaming: bool,
+        #[arg(required = true, value_name = "STATE")]
+        inputs: Vec<PathBuf>,
+    },
+    /// Test the saved histogram of --metric against a reference distribution
+    Fit {
+        /// CSV file with a value and its probability or count per line, a header is skipped
+        #[arg(long, value_name = "PATH")]
+        reference: PathBuf,
+    },
+    /// Print the quantiles of two state files side by side as CSV, for a Q-Q plot
+    Qq {
+        #[arg(value_name = "STATE_A")]
+        a: PathBuf,
+        #[arg(value_name = "STATE_B")]
+        b: PathBuf,
+        /// Number of steps between the 0 and 1 quantile
+        #[arg(long, default_value_t = 100, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        steps: usize,
+    },
+    /// Check a state file for signs of corruption, exiting with 1 if any are found
+    ///
+    /// The run's `manifest.json`, as written by --output-dir, is read from the directory of the
+    /// state file if present.
+    Validate {
+        #[arg(value_name = "STATE")]
+        state: PathBuf,
+        /// Largest game length to accept, by default the --max-turns of the manifest
+        #[arg(long)]
+        max_turns: Option<usize>,
+    },
+    /// Print how many games fall on every combination of two metrics as CSV
+    Joint {
+        #[arg(long, value_enum, default_value_t = Metric::Length)]
+        x: Metric,
+        #[arg(long, value_enum, default_value_t = Metric::WarCount)]
+        y: Metric,
+        /// First seed of the range
+        #[arg(long, default_value_t = 0)]
+        seed_start: u64,
+        /// End of the seed range, exclusive
+        #[arg(long, default_value_t = 10_000)]
+        seed_end: u64,
+    },
+    /// Print player 0's win rate and the mean game length for a range of handicaps as CSV
+    HandicapSweep {
+        /// Games played per handicap
+        #[arg(long, default_value_t = 10_000)]
+        num: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Largest handicap, 26 gives player 0 the whole deck
+        #[arg(long, default_value_t = game::STANDARD_DECK_SIZE / 2)]
+        max_handicap: usize,
+        #[arg(long, default_value_t = 2, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        step: usize,
+    },
+    /// Compare dealing the same shuffled decks in chunks of different sizes, up to one half of
+    /// the deck per player
+    CompareDeals {
+        /// First seed of the range
+        #[arg(long, default_value_t = 0)]
+        seed_start: u64,
+        /// End of the seed range, exclusive
+        #[arg(long, default_value_t = 10_000)]
+        seed_end: u64,
+    },
+    /// Compare how many games end with and without shuffling the pots over a range of seeds
+    CompareShuffle {
+        /// First seed of the range
+        #[arg(long, default_value_t = 0)]
+        seed_start: u64,
+        /// End of the seed range, exclusive
+        #[arg(long, default_value_t = 1_000)]
+        seed_end: u64,
+        /// Games still running after this many turns count as not ending
+        #[arg(long, default_value_t = 10_000)]
+        max_turns: usize,
+    },
+}
+
+/// Parses a number of games, also accepting scientific notation such as `1e6`
+fn parse_game_count(s: &str) -> Result<usize, String> {
+    if let Ok(count) = s.parse() {
+        return Ok(count);
+    }
+    match s.parse::<f64>() {
+        Ok(count) if count >= 0.0 && count.fract() == 0.0 => Ok(count as usize),
+        _ => Err(format!("`{}` is not a number of games", s)),
+    }
+}
+
+/// Path of the state files without extension, the game length keeps the original `state` name
+fn file_stem(metric: Metric) -> String {
+    match metric {
+        Metric::Length => "./state".to_string(),
+        _ => format!("./state_{}", metric.name()),
+    }
 }
 
 fn main() {
-    let args = Args::parse();
-    histogram_length_of_game(args.num);
+    if let Err(error) = run(Args::parse()) {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), GowsimError> {
+    let rules = game::GameRules {
+        inverted: args.inverted,
+        pot_shuffle: if args.no_shuffle {
+            game::PotShuffle::WinnerFirst
+        } else {
+            game::PotShuffle::Random
+        },
+        handicap: args.handicap,
+        deal_chunk: args.deal_chunk,
+        burn_count: args.burn,
+        first_war: args.force_first_war,
+        war_length: args.war_length,
+        include_trigger_cards: !args.exclude_trigger_cards,
+        war_tie: args.war_tie,
+        color_tiebreak: args.color_tiebreak,
+        rng: args.rng,
+        ..game::GameRules::default()
+    };
+    rules.validate()?;
+    let deal = if args.portable_deal {
+        game::Deal::Portable
+    } else if args.no_shuffle_deal {
+        game::Deal::Sorted
+    } else if let Some(riffles) = args.riffle_deal {
+        game::Deal::Riffled(riffles)
+    } else {
+        game::Deal::Shuffled
+    };
+    match args.command {
+        Some(Command::Step { seed }) => {
+            let seed = seed.unwrap_or_else(rand::random);
+            step_interactively(Game::dealt(seed, rules, deal), seed, &args);
+            return Ok(());
+        }
+        Some(Command::Inspect { seed, events }) => {
+            let game = Game::dealt(seed, rules, deal);
+            inspect(game, seed, events, &args);
+            return Ok(());
+        }
+        Some(Command::Merge {
+            output,
+            streaming,
+            inputs,
+        }) => {
+            merge_state_files(&inputs, &output, streaming, args.format_version)?;
+            return Ok(());
+        }
+        Some(Command::Fit { reference }) => {
+            let state = load_state_from_disk(&file_stem(args.metric))?;
+            if state.is_empty() {
+                println!("No {} histogram saved yet", args.metric.name());
+                return Ok(());
+            }
+            let (statistic, p_value) = analysis::ks_test(&state, &read_reference(&reference)?)?;
+            println!(
+                "Kolmogorov-Smirnov statistic {} with p-value {} over {} games",
+                format_number(statistic, args.precision.max(4)),
+                format_number(p_value, args.precision.max(4)),
+                state.values().sum::<u64>()
+            );
+            return Ok(());
+        }
+        Some(Command::Qq { a, b, steps }) => {
+            let read = |path: &Path| decode_state(&fs::read(path).map_err(at_path(path))?);
+            let (a, b) = (read(&a)?, read(&b)?);
+            if a.is_empty() || b.is_empty() {
+                println!("Both states need at least one game");
+                return Ok(());
+            }
+            let rows = (0..=steps).map(|step| {
+                let q = step as f64 / steps as f64;
+                [
+                    format_number(q, args.precision + 2),
+                    analysis::quantile(&a, q).unwrap().to_string(),
+                    analysis::quantile(&b, q).unwrap().to_string(),
+                ]
+            });
+            print!(
+                "{}",
+                format_csv(
+                    (!args.no_header).then_some(["quantile", "a", "b"]),
+                    rows,
+                    args.delimiter
+                )
+            );
+            return Ok(());
+        }
+        Some(Command::Validate { state, max_turns }) => {
+            let anomalies = validate_state(&state, max_turns);
+            for anomaly in &anomalies {
+                println!("{}", anomaly);
+            }
+            if !anomalies.is_empty() {
+                std::process::exit(1);
+            }
+            println!("{} looks sane", state.display());
+            return Ok(());
+        }
+        Some(Command::Joint {
+            x,
+            y,
+            seed_start,
+            seed_end,
+        }) => {
+            let simulation = Simulation {
+                threads: args.threads,
+                batch_size: args.batch_size,
+                rules,
+                max_turns: args.max_turns,
+                deal,
+                ..Simulation::from_seed_range(seed_start..seed_end)
+            };
+            let joint = simulation.run_with(|| JointHistogram::new(x, y), |_| {})?;
+            let mut rows: Vec<_> = joint.state.into_iter().collect();
+            rows.sort_unstable();
+            print!(
+                "{}",
+                format_csv(
+                    (!args.no_header).then_some([x.name(), y.name(), "count"]),
+                    rows.into_iter().map(|((x, y), count)| [
+                        x.to_string(),
+                        y.to_string(),
+                        count.to_string()
+                    ]),
+                    args.delimiter
+                )
+            );
+            return Ok(());
+        }
+        Some(Command::HandicapSweep {
+            num,
+            seed,
+            max_handicap,
+            step,
+        }) => {
+            let simulation = Simulation {
+                threads: args.threads,
+                batch_size: args.batch_size,
+                rules,
+                max_turns: args.max_turns,
+                deal,
+                ..Simulation::new(num, seed)
+            };
+            handicap_sweep(&simulation, max_handicap, step, &args)?;
+            return Ok(());
+        }
+        Some(Command::CompareDeals {
+            seed_start,
+            seed_end,
+        }) => {
+            let simulation = Simulation {
+                threads: args.threads,
+                batch_size: args.batch_size,
+                rules,
+                max_turns: args.max_turns,
+                deal,
+                ..Simulation::from_seed_range(seed_start..seed_end)
+            };
+            compare_deals(&simulation, &args)?;
+            return Ok(());
+        }
+        Some(Command::CompareShuffle {
+            seed_start,
+            seed_end,
+            max_turns,
+        }) => {
+            let simulation = Simulation {
+                threads: args.threads,
+                batch_size: args.batch_size,
+                rules,
+                max_turns: Some(max_turns),
+                deal,
+                ..Simulation::from_seed_range(seed_start..seed_end)
+            };
+            compare_shuffle(&simulation, &args)?;
+            return Ok(());
+        }
+        None => {}
+    }
+    if let Some(events_trace) = &args.events_trace {
+        let seed = parse_seed(&events_trace[0])?;
+        write_events_trace(
+            Path::new(&events_trace[1]),
+            seed,
+            &rules,
+            deal,
+            args.max_turns,
+        )?;
+        return Ok(());
+    }
+    if let Some(seed) = args.lead_autocorr {
+        print_lead_autocorrelation(seed, &rules, deal, &args);
+        return Ok(());
+    }
+    if let Some(frames) = &args.frames {
+        let seed = parse_seed(&frames[0])?;
+        write_frames(Path::new(&frames[1]), seed, &rules, deal, args.max_turns)?;
+        return Ok(());
+    }
+    let batch = match (args.seed_start, args.seed_end) {
+        (Some(seed_start), Some(seed_end)) => Simulation::from_seed_range(seed_start..seed_end),
+        _ => Simulation::new(
+            // A time limit without a count plays until the time runs out
+            args.num.unwrap_or(usize::MAX),
+            args.seed.unwrap_or_else(rand::random),
+        ),
+    };
+    let simulation = Simulation {
+        threads: args.threads,
+        batch_size: args.batch_size,
+        rules,
+        metric: args.metric,
+        max_turns: args.max_turns,
+        deal,
+        ..batch
+    };
+    histogram_length_of_game(&simulation, &args)
+}
+
+fn parse_seed(seed: &str) -> Result<u64, GowsimError> {
+    seed.parse()
+        .map_err(|_| GowsimError::InvalidConfig(format!("`{}` is not a seed", seed)))
 }
 
-type State = HashMap<usize, usize>;
+/// State files of the war pot size histogram
+const WAR_POT_SIZES_STEM: &str = "./state_war_pot_sizes";
+const FINISHING_RANK_STEM: &str = "./state_finishing_rank";
+/// State files of the histogram of the cards the loser held before the last turn
+const CLOSENESS_STEM: &str = "./state_closeness";
+/// State files of the histogram of the turn at which games repeated a state
+const CYCLE_TURN_STEM: &str = "./state_cycle_turn";
 
-fn load_state_from_disk() -> State {
-    fn load() -> Option<State> {
-        let mut file = File::open("./state.msgp").ok()?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).ok()?;
-        rmp_serde::from_slice::<State>(buffer.as_slice()).ok()
+/// Reads the state saved under `stem`, empty if there is none
+///
+/// The state file is read whichever format version it was written in. Without one the
+/// histogram is read back from a CSV or JSON file written next to it. Fails on files that
+/// cannot be decoded, such as ones of an unknown format version, rather than overwriting them.
+fn load_state_from_disk(stem: &str) -> Result<State, GowsimError> {
+    let path = format!("{}.msgp", stem);
+    if let Ok(bytes) = fs::read(&path) {
+        return decode_state(&bytes).map_err(|error| {
+            GowsimError::Serialization(format!("Cannot read state file {}: {}", path, error))
+        });
+    }
+    for format in [OutputFormat::Csv, OutputFormat::Json] {
+        let path = format!("{}.{}", stem, format.extension());
+        if let Ok(data) = fs::read_to_string(&path) {
+            return parse_histogram(&data, format).map_err(|error| {
+                GowsimError::Serialization(format!("Cannot read histogram {}: {}", path, error))
+            });
+        }
     }
-    load().unwrap_or(HashMap::new())
+    Ok(State::default())
 }
 
-fn save_state_to_disk(state: State) {
-    let mut csv_data = String::new();
-    csv_data.push_str("length, count\n");
-    let mut results: Vec<(usize, usize)> = state.clone().into_iter().map(|(k, v)| (k, v)).collect();
+/// Parses a histogram as rendered by [`format_state`] with any delimiter and header
+fn parse_histogram(data: &str, format: OutputFormat) -> Result<State, String> {
+    let collapsed = |row: &str| match row {
+        "other" => "The tail was collapsed into `other`, its values are lost".to_string(),
+        _ => format!(
+            "The histogram was clipped into `{}`, its values are lost",
+            row
+        ),
+    };
+    let is_summed = |row: &str| ["other", "underflow", "overflow"].contains(&row);
+    let mut state = State::new();
+    match format {
+        OutputFormat::Csv => {
+            for (index, line) in data.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let (value, count) = line
+                    .rsplit_once(|c: char| !c.is_ascii_digit())
+                    .map(|(value, count)| {
+                        (
+                            value.trim_end_matches(|c: char| !c.is_ascii_alphanumeric()),
+                            count,
+                        )
+                    })
+                    .unwrap_or((line, ""));
+                if is_summed(value) {
+                    return Err(collapsed(value));
+                }
+                match (value.parse(), count.parse()) {
+                    (Ok(value), Ok(count)) => {
+                        state.insert(value, count);
+                    }
+                    // The header, if the file was written with one
+                    _ if index == 0 => {}
+                    _ => return Err(format!("Malformed row {:?} on line {}", line, index + 1)),
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let rows: Vec<serde_json::Map<String, serde_json::Value>> =
+                serde_json::from_str(data).map_err(|error| error.to_string())?;
+            for row in rows {
+                let count = row.get("count").and_then(|count| count.as_u64());
+                let value = row.iter().find(|(key, _)| *key != "count");
+                match (value, count) {
+                    (Some((_, serde_json::Value::String(value))), _) if is_summed(value) => {
+                        return Err(collapsed(value))
+                    }
+                    (Some((_, value)), Some(count)) if value.is_u64() => {
+                        state.insert(value.as_u64().unwrap() as usize, count);
+                    }
+                    _ => return Err(format!("Malformed row {:?}", row)),
+                }
+            }
+        }
+    }
+    Ok(state)
+}
+
+/// Adds `state` onto the state saved under `stem`, saves the sum and returns it
+fn add_to_state_on_disk(
+    state: State,
+    stem: &str,
+    column_name: &str,
+    output: &Output,
+) -> Result<State, GowsimError> {
+    let mut saved = load_state_from_disk(stem)?;
+    merge_states(&mut saved, state);
+    save_state_to_disk(&saved, stem, column_name, output)?;
+    Ok(saved)
+}
+
+/// Renders the histogram sorted by value, between an `underflow` and an `overflow` row for the
+/// values outside the window and followed by an `other` row for the collapsed tail
+fn format_state(state: &State, column_name: &str, output: &Output) -> String {
+    let (state, underflow, overflow) = match output.window {
+        (None, None) => (state.clone(), 0, 0),
+        (min, max) => analysis::window(state, min.unwrap_or(0), max.unwrap_or(usize::MAX)),
+    };
+    let (state, other) = match output.tail_threshold {
+        Some(threshold) => analysis::collapse_tail(&state, threshold),
+        None => (state, 0),
+    };
+    let mut results: Vec<(usize, u64)> = state.into_iter().collect();
     results.sort_by_key(|(k, _)| *k);
-    for (length, chance) in results {
-        csv_data.push_str(&format!("{}, {}\n", length, chance));
+    let summed = |rows: &[(&'static str, u64)]| {
+        rows.iter()
+            .copied()
+            .filter(|(_, count)| *count > 0)
+            .collect::<Vec<_>>()
+    };
+    let before = summed(&[("underflow", underflow)]);
+    let after = summed(&[("overflow", overflow), ("other", other)]);
+    match output.format {
+        OutputFormat::Csv => {
+            let summed_row = |(name, count): &(&str, u64)| [name.to_string(), count.to_string()];
+            format_csv(
+                output.header.then_some([column_name, "count"]),
+                before
+                    .iter()
+                    .map(summed_row)
+                    .chain(
+                        results
+                            .iter()
+                            .map(|(value, count)| [value.to_string(), count.to_string()]),
+                    )
+                    .chain(after.iter().map(summed_row)),
+                output.delimiter,
+            )
+        }
+        OutputFormat::Json => {
+            let summed_row = |(name, count): &(&str, u64)| serde_json::json!({ column_name: name, "count": count });
+            let rows: Vec<serde_json::Value> = before
+                .iter()
+                .map(summed_row)
+                .chain(results.iter().map(
+                    |(value, count)| serde_json::json!({ column_name: value, "count": count }),
+                ))
+                .chain(after.iter().map(summed_row))
+                .collect();
+            serde_json::to_string(&rows).unwrap() + "\n"
+        }
+    }
+}
+
+/// Writes several histograms into one CSV file of `metric,value,count` rows, sorted by metric
+/// and value, the histograms told apart by their name in the `metric` column
+fn write_long_csv(
+    path: &Path,
+    histograms: &[(&str, &State)],
+    output: &Output,
+) -> Result<(), GowsimError> {
+    let mut rows: Vec<(&str, usize, u64)> = histograms
+        .iter()
+        .flat_map(|(name, state)| state.iter().map(|(value, count)| (*name, *value, *count)))
+        .collect();
+    rows.sort_unstable();
+    let csv = format_csv(
+        output.header.then_some(["metric", "value", "count"]),
+        rows.into_iter()
+            .map(|(name, value, count)| [name.to_string(), value.to_string(), count.to_string()]),
+        output.delimiter,
+    );
+    write_file(path, csv)?;
+    Ok(())
+}
+
+/// Joins the optional header and the rows into lines of fields separated by `delimiter`
+fn format_csv<const N: usize>(
+    header: Option<[&str; N]>,
+    rows: impl Iterator<Item = [String; N]>,
+    delimiter: char,
+) -> String {
+    let separator = delimiter.to_string();
+    let mut csv_data = String::new();
+    if let Some(header) = header {
+        csv_data.push_str(&header.join(&separator));
+        csv_data.push('\n');
+    }
+    for row in rows {
+        csv_data.push_str(&row.join(&separator));
+        csv_data.push('\n');
+    }
+    csv_data
+}
+
+fn save_state_to_disk(
+    state: &State,
+    stem: &str,
+    column_name: &str,
+    output: &Output,
+) -> Result<(), GowsimError> {
+    write_file(
+        format!("{}.{}", stem, output.format.extension()),
+        format_state(state, column_name, output),
+    )?;
+    let encoding = output
+        .state_encoding
+        .unwrap_or_else(|| StateEncoding::for_state(state));
+    let serialized_state = encode_state(state, encoding);
+    write_file(format!("{}.msgp", stem), serialized_state)?;
+    Ok(())
+}
+
+/// Reads `value,weight` lines, skipping those that do not parse like a header
+///
+/// Fails if no line parses, rather than fitting against an empty reference.
+fn read_reference(path: &Path) -> Result<HashMap<usize, f64>, GowsimError> {
+    let reference: HashMap<usize, f64> = fs::read_to_string(path)
+        .map_err(at_path(path))?
+        .lines()
+        .filter_map(|line| {
+            let (value, weight) = line.split_once(',')?;
+            Some((value.trim().parse().ok()?, weight.trim().parse().ok()?))
+        })
+        .collect();
+    if reference.is_empty() {
+        return Err(GowsimError::InvalidConfig(format!(
+            "{} has no `value,weight` lines",
+            path.display()
+        )));
+    }
+    Ok(reference)
+}
+
+/// Describes everything suspicious about the state file at `path`
+///
+/// Lengths are checked against `max_turns` or the limit in the manifest next to the file, the
+/// number of games against the manifest's.
+fn validate_state(path: &Path, max_turns: Option<usize>) -> Vec<String> {
+    let state = match fs::read(path)
+        .map_err(GowsimError::from)
+        .and_then(|bytes| decode_state(&bytes))
+    {
+        Ok(state) => state,
+        Err(error) => return vec![format!("Cannot read the state: {}", error)],
+    };
+    let manifest: Option<serde_json::Value> = path
+        .parent()
+        .and_then(|dir| fs::read_to_string(dir.join("manifest.json")).ok())
+        .and_then(|manifest| serde_json::from_str(&manifest).ok());
+    let mut anomalies = Vec::new();
+    let zeros = state.values().filter(|count| **count == 0).count();
+    if zeros > 0 {
+        anomalies.push(format!("{} values are stored with a count of 0", zeros));
+    }
+    let metric = manifest
+        .as_ref()
+        .and_then(|manifest| manifest["metric"].as_str());
+    let max_turns = max_turns.or_else(|| {
+        let max_turns = manifest.as_ref()?["max_turns"].as_u64()?;
+        Some(max_turns as usize)
+    });
+    if let (Some(max_turns), None | Some("length")) = (max_turns, metric) {
+        let too_long = state.keys().filter(|length| **length > max_turns).count();
+        if too_long > 0 {
+            anomalies.push(format!(
+                "{} lengths exceed the limit of {} turns",
+                too_long, max_turns
+            ));
+        }
+    }
+    let total: u64 = state
+        .values()
+        .fold(0, |total: u64, count| total.saturating_add(*count));
+    if total == u64::MAX {
+        anomalies.push("The total number of games saturated".to_string());
+    }
+    if let Some(num_games) = manifest
+        .as_ref()
+        .and_then(|manifest| manifest["num_games"].as_u64())
+    {
+        if total != num_games {
+            anomalies.push(format!(
+                "The state holds {} games, but the manifest describes {}",
+                total, num_games
+            ));
+        }
+    }
+    anomalies
+}
+
+/// Sums the state files at `inputs` into a state file at `output`, written with `encoding` or
+/// the smaller one of sparse and dense
+fn merge_state_files(
+    inputs: &[PathBuf],
+    output: &Path,
+    streaming: bool,
+    encoding: Option<StateEncoding>,
+) -> Result<(), GowsimError> {
+    if streaming {
+        if encoding.is_some_and(|encoding| encoding != StateEncoding::Dense) {
+            return Err(GowsimError::InvalidConfig(
+                "Streaming merges always write dense states".to_string(),
+            ));
+        }
+        let readers = inputs
+            .iter()
+            .map(|path| File::open(path).map(BufReader::new).map_err(at_path(path)))
+            .collect::<Result<Vec<_>, _>>()?;
+        return merge_dense_streaming(readers, BufWriter::new(create_file(output)?));
+    }
+    let mut merged = State::new();
+    for path in inputs {
+        merge_states(
+            &mut merged,
+            decode_state(&fs::read(path).map_err(at_path(path))?)?,
+        );
+    }
+    let encoding = encoding.unwrap_or_else(|| StateEncoding::for_state(&merged));
+    write_file(output, encode_state(&merged, encoding))?;
+    Ok(())
+}
+
+/// Writes the empirical CDF of `state` next to its state files
+fn save_cdf_to_disk(
+    state: &State,
+    stem: &str,
+    column_name: &str,
+    output: &Output,
+) -> Result<(), GowsimError> {
+    let cdf = analysis::cdf(state);
+    let data = match output.format {
+        OutputFormat::Csv => format_csv(
+            output
+                .header
+                .then_some([column_name, "cumulative_count", "cumulative_fraction"]),
+            cdf.into_iter().map(|(value, count, fraction)| {
+                [value.to_string(), count.to_string(), fraction.to_string()]
+            }),
+            output.delimiter,
+        ),
+        OutputFormat::Json => {
+            let rows: Vec<serde_json::Value> = cdf
+                .into_iter()
+                .map(|(value, count, fraction)| {
+                    serde_json::json!({
+                        column_name: value,
+                        "cumulative_count": count,
+                        "cumulative_fraction": fraction,
+                    })
+                })
+                .collect();
+            serde_json::to_string(&rows)? + "\n"
+        }
+    };
+    write_file(format!("{}_cdf.{}", stem, output.format.extension()), data)?;
+    Ok(())
+}
+
+/// Formats a statistic with `precision` decimal places, so all summary lines agree
+fn format_number(value: f64, precision: usize) -> String {
+    format!("{:.*}", precision, value)
+}
+
+#[derive(Serialize)]
+struct Progress {
+    elapsed: f64,
+    games_done: usize,
+    throughput: f64,
+    eta_secs: f64,
+    #[serde(skip)]
+    total_games: usize,
+}
+
+/// How progress updates are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProgressStyle {
+    /// A line of text per update
+    Lines,
+    /// A single line of text, overwritten by every update
+    Compact,
+    /// A JSON object per update on stderr
+    Json,
+}
+
+impl Progress {
+    fn report(&self, style: ProgressStyle, to_stderr: bool) {
+        match style {
+            ProgressStyle::Json => eprintln!("{}", serde_json::to_string(self).unwrap()),
+            ProgressStyle::Lines => status!(
+                to_stderr,
+                "Running for {:.1}s, simulating {:0.1} games per second ({:.1}% of run complete, {:.1}m remaining)",
+                self.elapsed,
+                self.throughput,
+                self.games_done as f64 / self.total_games as f64 * 100f64,
+                self.eta_secs / 60f64,
+            ),
+            ProgressStyle::Compact => {
+                // Padded to overwrite longer earlier lines
+                let line = format!("\r{:<79}", self.compact_line());
+                if to_stderr {
+                    eprint!("{}", line);
+                } else {
+                    print!("{}", line);
+                    io::stdout().flush().unwrap();
+                }
+            }
+        }
+    }
+
+    /// Status for [`ProgressStyle::Compact`], short enough for a terminal line
+    fn compact_line(&self) -> String {
+        format!(
+            "{:.0}s elapsed, {}/{} games, {:.1} games/s, ETA {:.1}m",
+            self.elapsed,
+            self.games_done,
+            self.total_games,
+            self.throughput,
+            self.eta_secs / 60f64
+        )
+    }
+}
+
+/// Everything recorded during a run of the binary, depending on the enabled outputs
+struct RunRecorder {
+    result: SimulationResult,
+    wins: WinCount,
+    war_pot_sizes: Option<WarPotSizes>,
+    finishing_ranks: Option<FinishingRank>,
+    closeness: Option<Closeness>,
+    endgame: Option<DrawnOutEndgame>,
+    comeback: Option<Comeback>,
+    cycles: Option<CycleDetection>,
+    deals: Option<DealLog>,
+    disorder: Option<DisorderLog>,
+    all_metrics: Option<AllMetrics>,
+    binary_log: Option<BinaryLog<BufWriter<File>>>,
+    longest: Option<LongestGame>,
+    first_turn: Option<FirstTurnWinner>,
+    #[cfg(feature = "profile")]
+    profile: Option<game::Profile>,
+}
+
+impl Recorder for RunRecorder {
+    fn start_game(&mut self, seed: u64, game: &Game) {
+        if let Some(cycles) = &mut self.cycles {
+            cycles.start_game(seed, game);
+        }
+        if let Some(deals) = &mut self.deals {
+            deals.start_game(seed, game);
+        }
+        if let Some(disorder) = &mut self.disorder {
+            disorder.start_game(seed, game);
+        }
+        if let Some(first_turn) = &mut self.first_turn {
+            first_turn.start_game(seed, game);
+        }
+        if let Some(finishing_ranks) = &mut self.finishing_ranks {
+            finishing_ranks.start_game(seed, game);
+        }
+        if let Some(closeness) = &mut self.closeness {
+            closeness.start_game(seed, game);
+        }
+        if let Some(endgame) = &mut self.endgame {
+            endgame.start_game(seed, game);
+        }
+        if let Some(comeback) = &mut self.comeback {
+            comeback.start_game(seed, game);
+        }
+    }
+
+    fn record_turn(&mut self, game: &Game, events: &[Event]) {
+        self.result.record_turn(game, events);
+        if let Some(war_pot_sizes) = &mut self.war_pot_sizes {
+            war_pot_sizes.record_turn(game, events);
+        }
+        if let Some(finishing_ranks) = &mut self.finishing_ranks {
+            finishing_ranks.record_turn(game, events);
+        }
+        if let Some(closeness) = &mut self.closeness {
+            closeness.record_turn(game, events);
+        }
+        if let Some(endgame) = &mut self.endgame {
+            endgame.record_turn(game, events);
+        }
+        if let Some(comeback) = &mut self.comeback {
+            comeback.record_turn(game, events);
+        }
+        if let Some(cycles) = &mut self.cycles {
+            cycles.record_turn(game, events);
+        }
+        if let Some(first_turn) = &mut self.first_turn {
+            first_turn.record_turn(game, events);
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.cycles
+            .as_ref()
+            .is_some_and(|cycles| cycles.should_stop())
+    }
+
+    fn finish_game(&mut self, seed: u64, game: &Game) {
+        self.result.finish_game(seed, game);
+        self.wins.finish_game(seed, game);
+        if let Some(binary_log) = &mut self.binary_log {
+            binary_log.finish_game(seed, game);
+        }
+        if let Some(disorder) = &mut self.disorder {
+            disorder.finish_game(seed, game);
+        }
+        if let Some(all_metrics) = &mut self.all_metrics {
+            all_metrics.finish_game(seed, game);
+        }
+        if let Some(longest) = &mut self.longest {
+            longest.finish_game(seed, game);
+        }
+        if let Some(first_turn) = &mut self.first_turn {
+            first_turn.finish_game(seed, game);
+        }
+        if let Some(finishing_ranks) = &mut self.finishing_ranks {
+            finishing_ranks.finish_game(seed, game);
+        }
+        if let Some(closeness) = &mut self.closeness {
+            closeness.finish_game(seed, game);
+        }
+        if let Some(endgame) = &mut self.endgame {
+            endgame.finish_game(seed, game);
+        }
+        if let Some(comeback) = &mut self.comeback {
+            comeback.finish_game(seed, game);
+        }
+        #[cfg(feature = "profile")]
+        if let Some(profile) = &mut self.profile {
+            profile.finish_game(seed, game);
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.result.merge(other.result);
+        self.wins.merge(other.wins);
+        if let (Some(war_pot_sizes), Some(other)) = (&mut self.war_pot_sizes, other.war_pot_sizes) {
+            war_pot_sizes.merge(other);
+        }
+        if let (Some(finishing_ranks), Some(other)) =
+            (&mut self.finishing_ranks, other.finishing_ranks)
+        {
+            finishing_ranks.merge(other);
+        }
+        if let (Some(closeness), Some(other)) = (&mut self.closeness, other.closeness) {
+            closeness.merge(other);
+        }
+        if let (Some(endgame), Some(other)) = (&mut self.endgame, other.endgame) {
+            endgame.merge(other);
+        }
+        if let (Some(comeback), Some(other)) = (&mut self.comeback, other.comeback) {
+            comeback.merge(other);
+        }
+        if let (Some(cycles), Some(other)) = (&mut self.cycles, other.cycles) {
+            cycles.merge(other);
+        }
+        if let (Some(deals), Some(other)) = (&mut self.deals, other.deals) {
+            deals.merge(other);
+        }
+        if let (Some(disorder), Some(other)) = (&mut self.disorder, other.disorder) {
+            disorder.merge(other);
+        }
+        if let (Some(all_metrics), Some(other)) = (&mut self.all_metrics, other.all_metrics) {
+            all_metrics.merge(other);
+        }
+        if let (Some(binary_log), Some(other)) = (&mut self.binary_log, other.binary_log) {
+            binary_log.merge(other);
+        }
+        if let (Some(longest), Some(other)) = (&mut self.longest, other.longest) {
+            longest.merge(other);
+        }
+        if let (Some(first_turn), Some(other)) = (&mut self.first_turn, other.first_turn) {
+            first_turn.merge(other);
+        }
+        #[cfg(feature = "profile")]
+        if let (Some(profile), Some(other)) = (&mut self.profile, other.profile) {
+            profile.merge(other);
+        }
+    }
+}
+
+fn write_deal_log(path: &PathBuf, deals: &DealLog) -> Result<(), GowsimError> {
+    let mut writer = BufWriter::new(create_file(path)?);
+    writer.write_all(b"seed,player0,player1\n")?;
+    writer.write_all(deals.lines.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Whether `game` has played the `max_turns` turns a single game is stopped at
+fn reached_max_turns(game: &Game, max_turns: Option<usize>) -> bool {
+    max_turns.is_some_and(|max_turns| game.stats.turn_number >= max_turns)
+}
+
+/// Writes a `turn,events` CSV line per turn of the game played with `seed` and dealt by
+/// `deal`, up to `max_turns`, a battle being a single event and wars producing more, the last
+/// turn also counts the end of the game
+fn write_events_trace(
+    path: &Path,
+    seed: u64,
+    rules: &game::GameRules,
+    deal: game::Deal,
+    max_turns: Option<usize>,
+) -> Result<(), GowsimError> {
+    let mut writer = BufWriter::new(create_file(path)?);
+    let mut game = Game::dealt(seed, rules.clone(), deal);
+    writer.write_all(b"turn,events\n")?;
+    while let Some(events) = game.step() {
+        writeln!(writer, "{},{}", game.stats.turn_number, events.len())?;
+        if reached_max_turns(&game, max_turns) {
+            break;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Lags in turns at which `--lead-autocorr` correlates the lead
+const LEAD_AUTOCORRELATION_LAGS: [usize; 6] = [1, 2, 5, 10, 20, 50];
+
+/// Prints the autocorrelation of the number of cards player 0 holds more than player 1 after
+/// each turn of the game played with `seed` and dealt by `deal`, up to `--max-turns`, at every
+/// lag of [`LEAD_AUTOCORRELATION_LAGS`]
+fn print_lead_autocorrelation(seed: u64, rules: &game::GameRules, deal: game::Deal, args: &Args) {
+    let mut game = Game::dealt(seed, rules.clone(), deal);
+    let mut leads = Vec::new();
+    while game.step().is_some() {
+        leads.push(game.players.0.count_cards() as f64 - game.players.1.count_cards() as f64);
+        if reached_max_turns(&game, args.max_turns) {
+            break;
+        }
+    }
+    let rows = LEAD_AUTOCORRELATION_LAGS.iter().map(|lag| {
+        [
+            lag.to_string(),
+            analysis::autocorrelation(&leads, *lag).map_or("undefined".to_string(), |r| {
+                format_number(r, args.precision + 2)
+            }),
+        ]
+    });
+    print!(
+        "{}",
+        format_csv(
+            (!args.no_header).then_some(["lag", "autocorrelation"]),
+            rows,
+            args.delimiter
+        )
+    );
+}
+
+/// Writes one JSON object per turn of the game played with `seed` and dealt by `deal`, up to
+/// `max_turns`, holding the cards played, who won them and the number of cards each player
+/// holds afterwards
+fn write_frames(
+    path: &Path,
+    seed: u64,
+    rules: &game::GameRules,
+    deal: game::Deal,
+    max_turns: Option<usize>,
+) -> Result<(), GowsimError> {
+    let mut game = Game::dealt(seed, rules.clone(), deal);
+    let mut frames = Vec::new();
+    while let Some(events) = game.step() {
+        let mut played = None;
+        let mut turn_winner = None;
+        let mut pot_size = 0;
+        let mut war_rounds = 0;
+        for event in &events {
+            match event {
+                Event::ShortBattle {
+                    winning_player_id,
+                    winning_card,
+                    losing_card,
+                    pot,
+                } => {
+                    played = Some(if *winning_player_id == 0 {
+                        (winning_card, losing_card)
+                    } else {
+                        (losing_card, winning_card)
+                    });
+                    turn_winner = Some(*winning_player_id);
+                    pot_size = pot.len();
+                }
+                Event::WarStart { top_cards, .. } => {
+                    played.get_or_insert((&top_cards.0, &top_cards.1));
+                    war_rounds += 1;
+                }
+                Event::WarEnd {
+                    winning_player_id,
+                    pot_size: war_pot_size,
+                    ..
+                } => {
+                    turn_winner = Some(*winning_player_id);
+                    pot_size = *war_pot_size;
+                }
+                _ => {}
+            }
+        }
+        frames.push(serde_json::json!({
+            "turn": game.stats.turn_number,
+            "played": played.map(|(a, b)| [a.to_string(), b.to_string()]),
+            "war_rounds": war_rounds,
+            "turn_winner": turn_winner,
+            "pot_size": pot_size,
+            "cards": [game.players.0.count_cards(), game.players.1.count_cards()],
+            "winner": game.winner(),
+        }));
+        if reached_max_turns(&game, max_turns) {
+            break;
+        }
+    }
+    let mut writer = BufWriter::new(create_file(path)?);
+    serde_json::to_writer(&mut writer, &frames)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the state and events of every turn of the game played with `seed` and dealt by `deal`
+fn write_replay(
+    path: &PathBuf,
+    seed: u64,
+    rules: &game::GameRules,
+    deal: game::Deal,
+    names: &PlayerNames,
+) -> Result<(), GowsimError> {
+    let mut writer = BufWriter::new(create_file(path)?);
+    let mut game = Game::dealt(seed, rules.clone(), deal);
+    writeln!(writer, "{}", game.named(names))?;
+    while let Some(events) = game.step() {
+        for event in events {
+            writeln!(writer, "  {}", event.named(names))?;
+        }
+        writeln!(writer, "{}", game.named(names))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Plays `game` dealt with `seed` turn by turn as directed on stdin, up to `--max-turns`
+fn step_interactively(mut game: Game, seed: u64, args: &Args) {
+    let names = &args.player_names;
+    println!("Game with seed {}", seed);
+    println!("Press Enter to play a turn, `r N` to play N turns, `q` to quit");
+    println!("{}", game.named(names));
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            return;
+        };
+        let turns = match line.trim().split_once(' ') {
+            None if line.trim().is_empty() => 1,
+            None if line.trim() == "q" => return,
+            Some(("r", n)) => match n.trim().parse() {
+                Ok(n) => n,
+                Err(_) => {
+                    println!("`{}` is not a number of turns", n.trim());
+                    continue;
+                }
+            },
+            _ => {
+                println!("Unknown command, press Enter, or use `r N` or `q`");
+                continue;
+            }
+        };
+        for _ in 0..turns {
+            let mut war_rounds = Vec::new();
+            let Some(events) = game.step_observed(|pot0, pot1| {
+                war_rounds.push(format!(
+                    "Pot: {} | {}",
+                    game::format_cards(pot0),
+                    game::format_cards(pot1)
+                ))
+            }) else {
+                break;
+            };
+            // Every war round ends right before the next nested war starts or the war ends
+            let mut war_rounds = war_rounds.into_iter();
+            let mut in_war = false;
+            for event in events {
+                match event {
+                    Event::WarStart { .. } | Event::WarEnd { .. } if in_war => {
+                        println!("  {}", war_rounds.next().unwrap());
+                    }
+                    _ => {}
+                }
+                in_war = match event {
+                    Event::WarStart { .. } => true,
+                    Event::WarEnd { .. } => false,
+                    _ => in_war,
+                };
+                println!("  {}", event.named(names));
+            }
+            if reached_max_turns(&game, args.max_turns) {
+                break;
+            }
+        }
+        println!("{}", game.named(names));
+        if game.winner().is_some() {
+            println!("Game over after {} turns", game.stats.turn_number);
+            return;
+        }
+        if reached_max_turns(&game, args.max_turns) {
+            println!("Stopped after {} turns", game.stats.turn_number);
+            return;
+        }
+    }
+}
+
+/// Plays `game` to the end, or `max_turns`, and prints its statistics
+fn inspect(mut game: Game, seed: u64, print_events: bool, args: &Args) {
+    let names = &args.player_names;
+    println!("Game with seed {}", seed);
+    let mut max_war_depth = 0;
+    while let Some(events) = game.step() {
+        // Every nested war of a turn starts with its own event
+        let war_depth = events
+            .iter()
+            .filter(|event| matches!(event, Event::WarStart { .. }))
+            .count();
+        max_war_depth = max_war_depth.max(war_depth);
+        if print_events {
+            for event in &events {
+                println!("Turn {}: {}", game.stats.turn_number, event.named(names));
+            }
+        }
+        if reached_max_turns(&game, args.max_turns) {
+            break;
+        }
+    }
+    println!("Turns: {}", game.stats.turn_number);
+    println!("Wars: {}", game.stats.war_count);
+    println!("War rounds: {}", game.stats.war_rounds);
+    println!("Deepest war: {}", max_war_depth);
+    println!("Lead changes: {}", game.stats.lead_changes);
+    match game.winner() {
+        Some(winner) => println!("Winner: {}", names.name(winner)),
+        None => println!(
+            "Winner: none, stopped after {} turns",
+            game.stats.turn_number
+        ),
+    }
+}
+
+/// Plays the games of `simulation` once per chunk size of [`game::GameRules::deal_chunk`]
+fn compare_deals(simulation: &Simulation, args: &Args) -> Result<(), GowsimError> {
+    if simulation.num_games == 0 {
+        println!("Nothing to simulate");
+        return Ok(());
+    }
+    println!(
+        "Dealing the decks of seeds {} to {}",
+        simulation.base_seed,
+        simulation
+            .base_seed
+            .wrapping_add(simulation.num_games as u64 - 1)
+    );
+    println!("Chunks of       | mean length | won by player 0");
+    for (name, deal_chunk) in [
+        ("1 (alternating)", 1),
+        ("2", 2),
+        ("3", 3),
+        ("4", 4),
+        ("13", 13),
+        ("26 (halves)", game::STANDARD_DECK_SIZE / 2),
+    ] {
+        let (result, wins) = Simulation {
+            rules: game::GameRules {
+                deal_chunk,
+                ..simulation.rules.clone()
+            },
+            ..simulation.clone()
+        }
+        .run_with(
+            || (SimulationResult::new(Metric::Length), WinCount::default()),
+            |_| {},
+        )?;
+        println!(
+            "{:<15} | {:>11} | {:>14}%",
+            name,
+            format_number(result.mean_turns(), args.precision),
+            format_number(wins.win_rate(0) * 100f64, args.precision)
+        );
+    }
+    Ok(())
+}
+
+/// Prints how many games of `simulation` end, loop or hit the turn cap with each pot shuffle
+fn compare_shuffle(simulation: &Simulation, args: &Args) -> Result<(), GowsimError> {
+    if simulation.num_games == 0 {
+        println!("Nothing to simulate");
+        return Ok(());
+    }
+    println!(
+        "Playing seeds {} to {} for at most {} turns",
+        simulation.base_seed,
+        simulation
+            .base_seed
+            .wrapping_add(simulation.num_games as u64 - 1),
+        simulation.max_turns.unwrap_or(usize::MAX)
+    );
+    println!("Pots         |   ended | looped | hit the cap");
+    for (name, pot_shuffle) in [
+        ("shuffled", game::PotShuffle::Random),
+        ("not shuffled", game::PotShuffle::WinnerFirst),
+    ] {
+        let termination = Simulation {
+            rules: game::GameRules {
+                pot_shuffle,
+                ..simulation.rules.clone()
+            },
+            ..simulation.clone()
+        }
+        .run_with(|| Termination::new(args.max_cycle_states), |_| {})?;
+        println!(
+            "{:<12} | {:>6}% | {:>6} | {:>11}",
+            name,
+            format_number(termination.finished_rate() * 100f64, args.precision),
+            termination.looped,
+            termination.capped
+        );
+    }
+    Ok(())
+}
+
+/// Plays the games of `simulation` once for every handicap up to `max_handicap`
+fn handicap_sweep(
+    simulation: &Simulation,
+    max_handicap: usize,
+    step: usize,
+    args: &Args,
+) -> Result<(), GowsimError> {
+    let mut rows = Vec::new();
+    for handicap in (0..=max_handicap).step_by(step) {
+        let (result, wins) = Simulation {
+            rules: game::GameRules {
+                handicap,
+                ..simulation.rules.clone()
+            },
+            ..simulation.clone()
+        }
+        .run_with(
+            || (SimulationResult::new(Metric::Length), WinCount::default()),
+            |_| {},
+        )?;
+        rows.push([
+            handicap.to_string(),
+            format_number(wins.win_rate(0), args.precision + 2),
+            format_number(result.mean_turns(), args.precision),
+        ]);
     }
-    write("./state.csv", csv_data).unwrap();
-    let serialized_state = rmp_serde::to_vec(&state).unwrap();
-    write("./state.msgp", serialized_state).unwrap();
+    print!(
+        "{}",
+        format_csv(
+            (!args.no_header).then_some(["handicap", "player0_win_rate", "mean_length"]),
+            rows.into_iter(),
+            args.delimiter
+        )
+    );
+    Ok(())
 }
 
-fn histogram_length_of_game(num_games: usize) {
-    let mut count_map = load_state_from_disk();
+fn histogram_length_of_game(simulation: &Simulation, args: &Args) -> Result<(), GowsimError> {
+    let progress_style = if args.progress_json {
+        ProgressStyle::Json
+    } else if args.compact_progress {
+        ProgressStyle::Compact
+    } else {
+        ProgressStyle::Lines
+    };
+    let output = Output::for_run(args, simulation);
+    let num_games = simulation.num_games;
+    if num_games == 0 {
+        status!(
+            args.stdout,
+            "Nothing to simulate, leaving the state on disk untouched"
+        );
+        return Ok(());
+    }
+    if args.warmup > 0 {
+        status!(args.stdout, "Warming up with {} games", args.warmup);
+        let _ = Simulation {
+            num_games: args.warmup,
+            ..simulation.clone()
+        }
+        .run(|_| {})?;
+    }
     let start = Instant::now();
     struct LastUpdateState {
         instant: Instant,
         count: usize,
     }
-    let mut last_update = LastUpdateState {
+    let last_update = Mutex::new(LastUpdateState {
         instant: Instant::now(),
         count: 0,
+    });
+    let deadline = args
+        .limit_time
+        .map(|secs| start + Duration::from_secs_f64(secs));
+    match args.limit_time {
+        Some(secs) => status!(args.stdout, "Simulating games for {} seconds", secs),
+        None => status!(args.stdout, "Simulating {} games", num_games),
+    }
+    let binary_log_writer = args
+        .binary_log
+        .as_ref()
+        .map(create_file)
+        .transpose()?
+        .map(|file| Arc::new(Mutex::new(BufWriter::new(file))));
+    let new_recorder = || RunRecorder {
+        result: SimulationResult::new(simulation.metric),
+        wins: WinCount::default(),
+        war_pot_sizes: args.war_pot_sizes.then(WarPotSizes::default),
+        finishing_ranks: args.finishing_rank_stats.then(FinishingRank::default),
+        closeness: args.closeness_stats.then(Closeness::default),
+        endgame: args
+            .endgame_stats
+            .then(|| DrawnOutEndgame::new(args.endgame_cards, args.endgame_turns)),
+        comeback: args.comeback_threshold.map(Comeback::new),
+        cycles: args
+            .detect_cycle
+            .then(|| CycleDetection::new(args.max_cycle_states)),
+        deals: args.log_deals.as_ref().map(|_| DealLog::default()),
+        disorder: args.log_disorder.as_ref().map(|_| DisorderLog::default()),
+        all_metrics: args.long_csv.as_ref().map(|_| AllMetrics::default()),
+        binary_log: binary_log_writer.clone().map(BinaryLog::new),
+        longest: args.find_longest.then(LongestGame::default),
+        first_turn: args.first_turn.then(FirstTurnWinner::default),
+        #[cfg(feature = "profile")]
+        profile: args.profile.then(game::Profile::default),
     };
-    println!("Simulating {} games", num_games);
-    for i in 0..num_games {
-        if i % 100000 == 0 && last_update.instant.elapsed().as_secs() >= 5 {
-            let throughput_per_sec =
-                (i - last_update.count + 1) as f64 / last_update.instant.elapsed().as_secs_f64();
-            println!(
-                "Running for {:.1}s, simulating {:0.1} games per second ({:.1}% of run complete, {:.1}m remaining)",
-                start.elapsed().as_secs_f64(),
-                throughput_per_sec,
-                i as f64 / num_games as f64 * 100f64,
-                (num_games - i) as f64 / throughput_per_sec / 60f64,
-            );
-            last_update = LastUpdateState {
+    let on_progress = |games_done: usize| {
+        let mut last_update = last_update.lock().unwrap();
+        if last_update.instant.elapsed().as_secs() >= 5 {
+            let throughput_per_sec = (games_done - last_update.count) as f64
+                / last_update.instant.elapsed().as_secs_f64();
+            Progress {
+                elapsed: start.elapsed().as_secs_f64(),
+                games_done,
+                throughput: throughput_per_sec,
+                eta_secs: match deadline {
+                    Some(deadline) => deadline
+                        .saturating_duration_since(Instant::now())
+                        .as_secs_f64()
+                        .min((num_games - games_done) as f64 / throughput_per_sec),
+                    None => (num_games - games_done) as f64 / throughput_per_sec,
+                },
+                total_games: num_games,
+            }
+            .report(progress_style, args.stdout);
+            *last_update = LastUpdateState {
                 instant: Instant::now(),
-                count: i,
+                count: games_done,
             }
         }
-        let mut game1 = game::Game::new();
-        loop {
-            let step = game1.step();
-            if step.is_none() {
-                break;
+    };
+    // Playing the games up to every snapshot as a run of their own gives the same results
+    let mut ends: Vec<usize> = args
+        .snapshot_at
+        .iter()
+        .copied()
+        .filter(|end| *end > 0 && *end < num_games)
+        .chain([num_games])
+        .collect();
+    ends.sort_unstable();
+    ends.dedup();
+    // A time limit is checked after every round of one batch per thread
+    let threads = match simulation.threads {
+        0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        threads => threads,
+    };
+    let segment_len = simulation.batch_size.max(1).saturating_mul(threads);
+    let mut recorder: Option<RunRecorder> = None;
+    let mut games_before = 0;
+    while games_before < num_games {
+        let end = match deadline {
+            Some(deadline) if games_before > 0 && Instant::now() >= deadline => break,
+            Some(_) => games_before.saturating_add(segment_len).min(num_games),
+            None => *ends
+                .iter()
+                .find(|end| **end > games_before)
+                .expect("The last end is the number of games"),
+        };
+        let part = Simulation {
+            base_seed: simulation.base_seed.wrapping_add(games_before as u64),
+            num_games: end - games_before,
+            ..simulation.clone()
+        }
+        .run_with(new_recorder, |games_done| {
+            on_progress(games_before + games_done)
+        })?;
+        let recorder = match &mut recorder {
+            Some(recorder) => {
+                recorder.merge(part);
+                recorder
             }
+            None => recorder.insert(part),
+        };
+        games_before = end;
+        if args.snapshot_at.contains(&end) {
+            fs::create_dir_all(&output.dir).map_err(at_path(&output.dir))?;
+            let stem = format!("{}_at{}", file_stem(simulation.metric), end);
+            save_state_to_disk(
+                &recorder.result.histogram,
+                &output.stem(&stem),
+                simulation.metric.name(),
+                &output,
+            )?;
         }
-        if let Some(before) = count_map.get(&game1.stats.turn_number) {
-            count_map.insert(game1.stats.turn_number, *before + 1);
-        } else {
-            count_map.insert(game1.stats.turn_number, 1);
+    }
+    let recorder = recorder.expect("At least one game is simulated");
+    let stopped_early = games_before < num_games;
+    // Everything below describes the games actually played
+    let simulation = &Simulation {
+        num_games: games_before,
+        ..simulation.clone()
+    };
+    let num_games = games_before;
+    let output = match args.limit_time {
+        Some(secs) => {
+            if stopped_early {
+                status!(
+                    args.stdout,
+                    "Stopped after the time limit of {} seconds with {} games",
+                    secs,
+                    num_games
+                );
+            }
+            // Name the run after the number of games it ended up with
+            Output::for_run(args, simulation)
+        }
+        None => output,
+    };
+    if progress_style == ProgressStyle::Compact && last_update.lock().unwrap().count > 0 {
+        // End the overwritten line
+        status!(args.stdout, "");
+    }
+    let result = recorder.result;
+    status!(
+        args.stdout,
+        "Simulated {} games per second",
+        format_number(
+            num_games as f64 / start.elapsed().as_secs_f64(),
+            args.precision
+        )
+    );
+    status!(
+        args.stdout,
+        "Done! Games took {} turns and {} wars on average, {} won {}% of them.",
+        format_number(result.mean_turns(), args.precision),
+        format_number(
+            result.totals.war_count as f64 / result.total_games as f64,
+            args.precision
+        ),
+        args.player_names.name(0),
+        format_number(recorder.wins.win_rate(0) * 100f64, args.precision)
+    );
+    if result.draws > 0 {
+        status!(
+            args.stdout,
+            "{} games were stopped without a winner",
+            result.draws
+        );
+    }
+    let modes = analysis::modes(&result.histogram);
+    status!(
+        args.stdout,
+        "Most common {}: {} ({} games)",
+        simulation.metric.name(),
+        modes
+            .iter()
+            .map(|mode| mode.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        result.histogram[&modes[0]]
+    );
+    if let Some(summary) = args
+        .box_plot
+        .then(|| analysis::five_number_summary(&result.histogram))
+        .flatten()
+    {
+        status!(args.stdout, "Box plot of {}:", simulation.metric.name());
+        for (name, value) in [
+            ("min", summary.min),
+            ("q1", summary.q1),
+            ("median", summary.median),
+            ("q3", summary.q3),
+            ("max", summary.max),
+            ("iqr", summary.iqr()),
+        ] {
+            status!(args.stdout, "  {:<6} {:>6}", name, value);
+        }
+    }
+    if args.means {
+        let histogram = &result.histogram;
+        let format_mean = |mean: Option<f64>| {
+            mean.map_or("undefined".to_string(), |mean| {
+                format_number(mean, args.precision)
+            })
+        };
+        status!(
+            args.stdout,
+            "Geometric mean {}: {}, harmonic mean: {}, ignoring games at 0",
+            simulation.metric.name(),
+            format_mean(analysis::geometric_mean(histogram)),
+            format_mean(analysis::harmonic_mean(histogram))
+        );
+    }
+    if let (Some(path), Some(deals)) = (&args.log_deals, &recorder.deals) {
+        write_deal_log(path, deals)?;
+    }
+    if let (Some(path), Some(all_metrics)) = (&args.long_csv, &recorder.all_metrics) {
+        let histograms: Vec<_> = all_metrics
+            .states
+            .iter()
+            .map(|(metric, state)| (metric.name(), state))
+            .collect();
+        write_long_csv(path, &histograms, &output)?;
+    }
+    if let (Some(path), Some(disorder)) = (&args.log_disorder, &recorder.disorder) {
+        let mut writer = BufWriter::new(create_file(path)?);
+        writer.write_all(b"seed,disorder,length\n")?;
+        writer.write_all(disorder.lines.as_bytes())?;
+        writer.flush()?;
+    }
+    if let Some(binary_log) = recorder.binary_log {
+        binary_log.flush()?;
+    }
+    if let Some(longest) = recorder.longest {
+        match longest.longest {
+            Some((seed, turns)) => {
+                status!(
+                    args.stdout,
+                    "Longest game: seed {} with {} turns",
+                    seed,
+                    turns
+                );
+                if let Some(path) = &args.replay {
+                    write_replay(
+                        path,
+                        seed,
+                        &simulation.rules,
+                        simulation.deal,
+                        &args.player_names,
+                    )?;
+                }
+            }
+            None => status!(args.stdout, "No game finished"),
         }
     }
-    println!("Done! Saving to disk.");
-    save_state_to_disk(count_map);
+    if let Some(first_turn) = &recorder.first_turn {
+        let names = &args.player_names;
+        let won_by = format!("game won by {}", names.name(0));
+        status!(
+            args.stdout,
+            "First turn won by | {} | {}",
+            won_by,
+            names.name(1)
+        );
+        for (player_id, row) in first_turn.games.iter().enumerate() {
+            status!(
+                args.stdout,
+                "{:<17} | {:>won_by$} | {:>name$}",
+                names.name(player_id),
+                row[0],
+                row[1],
+                won_by = won_by.len(),
+                name = names.name(1).len()
+            );
+        }
+        status!(
+            args.stdout,
+            "The winner of the first turn won {}% of the games",
+            format_number(first_turn.first_turn_win_rate() * 100f64, args.precision)
+        );
+    }
+    if let Some(finishing_ranks) = &recorder.finishing_ranks {
+        let mut ranks: Vec<(usize, u64)> = finishing_ranks.state.clone().into_iter().collect();
+        ranks.sort_unstable();
+        status!(
+            args.stdout,
+            "Games were finished by {}",
+            ranks
+                .iter()
+                .map(|(strength, count)| format!(
+                    "{} {}x",
+                    game::Face::from_strength(*strength).unwrap(),
+                    count
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    if let Some(closeness) = &recorder.closeness {
+        let games: u64 = closeness.state.values().sum();
+        let cards: u64 = closeness
+            .state
+            .iter()
+            .map(|(cards, count)| *cards as u64 * count)
+            .sum();
+        let single_card = closeness.state.get(&1).copied().unwrap_or(0);
+        status!(
+            args.stdout,
+            "Before the last turn the loser held {} cards on average, a single card in {}% of games",
+            format_number(cards as f64 / games as f64, args.precision),
+            format_number(single_card as f64 / games as f64 * 100f64, args.precision)
+        );
+    }
+    if let Some(endgame) = &recorder.endgame {
+        status!(
+            args.stdout,
+            "{} of {} games spent more than {} turns with a player holding fewer than {} cards",
+            endgame.drawn_out,
+            endgame.games,
+            endgame.turns,
+            endgame.cards
+        );
+    }
+    if let Some(comeback) = &recorder.comeback {
+        status!(
+            args.stdout,
+            "{}% of won games were won by a player once behind by more than {} cards",
+            format_number(comeback.comeback_rate() * 100f64, args.precision),
+            comeback.threshold
+        );
+    }
+    #[cfg(feature = "profile")]
+    if let Some(profile) = &recorder.profile {
+        let total = (profile.battle_nanos + profile.war_nanos) as f64;
+        status!(
+            args.stdout,
+            "Spent {}ms in battles ({}%) and {}ms in wars ({}%)",
+            format_number(profile.battle_nanos as f64 / 1e6, args.precision),
+            format_number(profile.battle_nanos as f64 / total * 100f64, args.precision),
+            format_number(profile.war_nanos as f64 / 1e6, args.precision),
+            format_number(profile.war_nanos as f64 / total * 100f64, args.precision),
+        );
+    }
+    if let Some(cycles) = &recorder.cycles {
+        status!(
+            args.stdout,
+            "{} of {} games returned to an earlier state",
+            cycles.games_with_cycle(),
+            num_games
+        );
+    }
+    if args.report_memory {
+        let dense_bytes = result.histogram.keys().max().map_or(0, |max| (max + 1) * 8);
+        status!(
+            args.stdout,
+            "Histogram of {} values takes about {} KiB in memory, {} KiB as a dense array",
+            result.histogram.len(),
+            format_number(
+                analysis::estimate_memory(&result.histogram) as f64 / 1024f64,
+                args.precision
+            ),
+            format_number(dense_bytes as f64 / 1024f64, args.precision)
+        );
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(path) = &args.parquet {
+        let writer = BufWriter::new(create_file(path)?);
+        gowsim::parquet::write_histogram(&result.histogram, simulation.metric.name(), writer)?;
+    }
+    if args.stdout {
+        print!(
+            "{}",
+            format_state(&result.histogram, simulation.metric.name(), &output)
+        );
+        return Ok(());
+    }
+    if args.no_save {
+        return Ok(());
+    }
+    if args.output_dir.is_some() {
+        status!(args.stdout, "Saving to {}.", output.dir.display());
+        fs::create_dir_all(&output.dir).map_err(at_path(&output.dir))?;
+        write_manifest(&output, simulation)?;
+    } else {
+        status!(args.stdout, "Saving to disk.");
+    }
+    let stem = output.stem(&file_stem(simulation.metric));
+    let saved = add_to_state_on_disk(result.histogram, &stem, simulation.metric.name(), &output)?;
+    if args.cdf {
+        save_cdf_to_disk(&saved, &stem, simulation.metric.name(), &output)?;
+    }
+    if let Some(war_pot_sizes) = recorder.war_pot_sizes {
+        add_to_state_on_disk(
+            war_pot_sizes.state,
+            &output.stem(WAR_POT_SIZES_STEM),
+            "pot_size",
+            &output,
+        )?;
+    }
+    if let Some(finishing_ranks) = recorder.finishing_ranks {
+        add_to_state_on_disk(
+            finishing_ranks.state,
+            &output.stem(FINISHING_RANK_STEM),
+            "strength",
+            &output,
+        )?;
+    }
+    if let Some(closeness) = recorder.closeness {
+        add_to_state_on_disk(
+            closeness.state,
+            &output.stem(CLOSENESS_STEM),
+            "loser_cards",
+            &output,
+        )?;
+    }
+    if let Some(cycles) = recorder.cycles {
+        add_to_state_on_disk(cycles.state, &output.stem(CYCLE_TURN_STEM), "turn", &output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_formatted_with_the_given_precision() {
+        assert_eq!(format_number(2.0 / 3.0, 3), "0.667");
+        assert_eq!(format_number(116.9, 3), "116.900");
+        assert_eq!(format_number(49.96, 1), "50.0");
+        assert_eq!(format_number(49.96, 0), "50");
+    }
+
+    /// Path in the temporary directory for a file written by a test
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gowsim-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn events_trace_has_a_row_per_turn_with_more_events_for_wars() {
+        let path = temp_path("events.csv");
+        let rules = game::GameRules::default();
+        write_events_trace(&path, 21, &rules, game::Deal::Shuffled, None).unwrap();
+        let trace = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut lines = trace.lines();
+        assert_eq!(lines.next(), Some("turn,events"));
+        let counts: Vec<usize> = lines
+            .enumerate()
+            .map(|(index, line)| {
+                let (turn, events) = line.split_once(',').unwrap();
+                assert_eq!(turn.parse::<usize>().unwrap(), index + 1);
+                events.parse().unwrap()
+            })
+            .collect();
+        let mut game = Game::dealt(21, rules, game::Deal::Shuffled);
+        let mut war_turns = Vec::new();
+        while let Some(events) = game.step() {
+            war_turns.push(
+                events
+                    .iter()
+                    .any(|event| matches!(event, Event::WarStart { .. })),
+            );
+        }
+        assert_eq!(counts.len(), game.stats.turn_number);
+        let mean = |war| {
+            let counts: Vec<usize> = counts
+                .iter()
+                .zip(&war_turns)
+                .filter(|(_, is_war)| **is_war == war)
+                .map(|(count, _)| *count)
+                .collect();
+            counts.iter().sum::<usize>() as f64 / counts.len() as f64
+        };
+        assert!(game.stats.war_count > 0);
+        assert!(mean(true) > mean(false));
+        assert!(counts
+            .iter()
+            .zip(&war_turns)
+            .all(|(count, is_war)| !*is_war || *count >= 2));
+    }
+
+    #[test]
+    fn events_trace_stops_at_max_turns() {
+        let path = temp_path("events-capped.csv");
+        let rules = game::GameRules::default();
+        write_events_trace(&path, 21, &rules, game::Deal::Shuffled, Some(10)).unwrap();
+        let trace = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(trace.lines().count(), 11);
+    }
+
+    #[test]
+    fn frames_hold_every_turn_and_end_with_the_winner() {
+        let path = temp_path("frames.json");
+        let rules = game::GameRules::default();
+        write_frames(&path, 21, &rules, game::Deal::Shuffled, None).unwrap();
+        let frames: Vec<serde_json::Value> =
+            serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut game = Game::dealt(21, rules, game::Deal::Shuffled);
+        game.play_to_completion();
+        assert_eq!(frames.len(), game.turn_number());
+        for (index, frame) in frames.iter().enumerate() {
+            assert_eq!(frame["turn"], index + 1);
+            let cards = frame["cards"].as_array().unwrap();
+            let held: u64 = cards.iter().map(|count| count.as_u64().unwrap()).sum();
+            assert!(held <= 52);
+        }
+        let last = frames.last().unwrap();
+        assert_eq!(last["winner"], serde_json::json!(game.winner()));
+        assert!(last["winner"].is_u64());
+        assert!(frames[..frames.len() - 1]
+            .iter()
+            .all(|frame| frame["winner"].is_null()));
+    }
+
+    #[test]
+    fn state_is_resumed_from_every_format() {
+        let state = State::from([(2, 4), (60, 1), (61, u64::MAX)]);
+        let stem = temp_path("resume");
+        let stem = stem.to_str().unwrap();
+        let msgp = format!("{}.msgp", stem);
+        for encoding in [
+            StateEncoding::Legacy,
+            StateEncoding::Sparse,
+            StateEncoding::Dense,
+        ] {
+            fs::write(&msgp, encode_state(&state, encoding)).unwrap();
+            assert_eq!(load_state_from_disk(stem).unwrap(), state, "{:?}", encoding);
+        }
+        fs::remove_file(&msgp).unwrap();
+        assert_eq!(load_state_from_disk(stem).unwrap(), State::new());
+        for (format, delimiter, header) in [
+            (OutputFormat::Csv, ',', true),
+            (OutputFormat::Csv, ';', false),
+            (OutputFormat::Json, ',', true),
+        ] {
+            let output = Output {
+                format,
+                delimiter,
+                header,
+                tail_threshold: None,
+                window: (None, None),
+                state_encoding: None,
+                dir: PathBuf::from("."),
+            };
+            let path = format!("{}.{}", stem, format.extension());
+            fs::write(&path, format_state(&state, "length", &output)).unwrap();
+            assert_eq!(load_state_from_disk(stem).unwrap(), state);
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn collapsed_histograms_are_not_resumed() {
+        let stem = temp_path("resume-collapsed");
+        let stem = stem.to_str().unwrap();
+        let path = format!("{}.csv", stem);
+        fs::write(&path, "length,count\n40,3\nother,2\n").unwrap();
+        let result = load_state_from_disk(stem);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(GowsimError::Serialization(_))));
+    }
+
+    #[test]
+    fn compact_progress_line_holds_elapsed_time_throughput_and_eta() {
+        let progress = Progress {
+            elapsed: 12.4,
+            games_done: 50_000,
+            throughput: 4032.25,
+            eta_secs: 90.0,
+            total_games: 200_000,
+        };
+        let line = progress.compact_line();
+        assert_eq!(
+            line,
+            "12s elapsed, 50000/200000 games, 4032.2 games/s, ETA 1.5m"
+        );
+        assert!(line.len() <= 79);
+        assert!(!line.contains(['\r', '\n']));
+    }
+
+    #[test]
+    fn inconsistent_states_are_flagged() {
+        let dir = temp_path("validate");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.msgp");
+        let manifest = serde_json::json!({
+            "metric": "length",
+            "num_games": 10,
+            "max_turns": 500,
+        });
+        fs::write(dir.join("manifest.json"), manifest.to_string()).unwrap();
+        let sane = State::from([(120, 6), (480, 4)]);
+        fs::write(&path, encode_state(&sane, StateEncoding::Sparse)).unwrap();
+        assert!(validate_state(&path, None).is_empty());
+        // A lower limit given on the command line replaces the manifest's
+        assert_eq!(
+            validate_state(&path, Some(200)),
+            ["1 lengths exceed the limit of 200 turns"]
+        );
+
+        let tampered = State::from([(120, 6), (900, 5), (50, 0)]);
+        fs::write(&path, encode_state(&tampered, StateEncoding::Sparse)).unwrap();
+        assert_eq!(
+            validate_state(&path, None),
+            [
+                "1 values are stored with a count of 0",
+                "1 lengths exceed the limit of 500 turns",
+                "The state holds 11 games, but the manifest describes 10",
+            ]
+        );
+
+        fs::write(&path, [9, 1, 2]).unwrap();
+        let anomalies = validate_state(&path, None);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(anomalies.len(), 1);
+        assert!(anomalies[0].starts_with("Cannot read the state"));
+    }
+
+    #[test]
+    fn long_csv_round_trips_every_histogram() {
+        let length = State::from([(120, 3), (45, 1), (300, u64::MAX)]);
+        let war_count = State::from([(0, 2), (7, 2)]);
+        let empty = State::new();
+        let histograms = [
+            ("war_count", &war_count),
+            ("length", &length),
+            ("lead_changes", &empty),
+        ];
+        let path = temp_path("long.csv");
+        let output = Output {
+            format: OutputFormat::Csv,
+            delimiter: ',',
+            header: true,
+            tail_threshold: None,
+            window: (None, None),
+            state_encoding: None,
+            dir: PathBuf::from("."),
+        };
+        write_long_csv(&path, &histograms, &output).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("metric,value,count"));
+        let mut read: HashMap<String, State> = HashMap::new();
+        let mut keys = Vec::new();
+        for line in lines {
+            let [metric, value, count]: [&str; 3] =
+                line.split(',').collect::<Vec<_>>().try_into().unwrap();
+            keys.push((metric.to_string(), value.parse::<usize>().unwrap()));
+            read.entry(metric.to_string())
+                .or_default()
+                .insert(value.parse().unwrap(), count.parse().unwrap());
+        }
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(read.len(), 2);
+        assert_eq!(read["length"], length);
+        assert_eq!(read["war_count"], war_count);
+    }
+
+    #[test]
+    fn windowed_histograms_are_written_with_underflow_and_overflow_rows() {
+        let state = State::from([(5, 2), (10, 3), (50, 7), (101, 4)]);
+        let output = Output {
+            format: OutputFormat::Csv,
+            delimiter: ',',
+            header: true,
+            tail_threshold: None,
+            window: (Some(10), Some(100)),
+            state_encoding: None,
+            dir: PathBuf::from("."),
+        };
+        assert_eq!(
+            format_state(&state, "length", &output),
+            "length,count\nunderflow,2\n10,3\n50,7\noverflow,4\n"
+        );
+        // An empty side leaves its row out
+        let output = Output {
+            window: (None, Some(100)),
+            ..output
+        };
+        assert_eq!(
+            format_state(&state, "length", &output),
+            "length,count\n5,2\n10,3\n50,7\noverflow,4\n"
+        );
+    }
 }
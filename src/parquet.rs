@@ -0,0 +1,140 @@
+use std::{io::Write, sync::Arc};
+
+use parquet::{
+    basic::{Repetition, Type as PhysicalType},
+    data_type::Int64Type,
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::types::Type,
+};
+
+use crate::{GowsimError, State};
+
+/// Writes `state` sorted by value as a Parquet file of two required 64 bit integer columns
+/// named `column_name` and `count`
+///
+/// The file holds a single uncompressed row group, which pandas, Polars and DuckDB read without
+/// any options. Counts beyond `i64::MAX` are written as `i64::MAX`.
+pub fn write_histogram(
+    state: &State,
+    column_name: &str,
+    writer: impl Write + Send,
+) -> Result<(), GowsimError> {
+    let mut rows: Vec<(usize, u64)> = state
+        .iter()
+        .map(|(value, count)| (*value, *count))
+        .collect();
+    rows.sort_unstable();
+    let saturating = |value: u64| i64::try_from(value).unwrap_or(i64::MAX);
+    let columns: [Vec<i64>; 2] = [
+        rows.iter()
+            .map(|(value, _)| saturating(*value as u64))
+            .collect(),
+        rows.iter().map(|(_, count)| saturating(*count)).collect(),
+    ];
+    let fields = [column_name, "count"]
+        .into_iter()
+        .map(|name| {
+            Type::primitive_type_builder(name, PhysicalType::INT64)
+                .with_repetition(Repetition::REQUIRED)
+                .build()
+                .map(Arc::new)
+        })
+        .collect::<Result<_, _>>()?;
+    let schema = Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()?;
+    let properties = WriterProperties::builder()
+        .set_created_by(concat!("gowsim version ", env!("CARGO_PKG_VERSION")).to_string())
+        .build();
+    let mut file = SerializedFileWriter::new(writer, Arc::new(schema), Arc::new(properties))?;
+    let mut row_group = file.next_row_group()?;
+    for values in &columns {
+        let mut column = row_group
+            .next_column()?
+            .expect("The schema has a column for every vector of values");
+        column
+            .typed::<Int64Type>()
+            .write_batch(values, None, None)?;
+        column.close()?;
+    }
+    row_group.close()?;
+    file.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+
+    use parquet::{
+        file::reader::{FileReader, SerializedFileReader},
+        record::RowAccessor,
+    };
+
+    use super::*;
+
+    /// Leaf column of a file read back, as its name, type and repetition
+    type Leaf = (String, PhysicalType, Repetition);
+
+    /// Writes `state` to a temporary file and reads it back with the `parquet` crate's reader,
+    /// returning its leaf columns and rows
+    fn write_and_read(state: &State, name: &str) -> (Vec<Leaf>, Vec<(i64, i64)>) {
+        let path = std::env::temp_dir().join(format!("gowsim-{}-{}", std::process::id(), name));
+        write_histogram(state, "length", File::create(&path).unwrap()).unwrap();
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        let columns = metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| {
+                let leaf = column.self_type();
+                (
+                    column.name().to_string(),
+                    leaf.get_physical_type(),
+                    leaf.get_basic_info().repetition(),
+                )
+            })
+            .collect();
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (row.get_long(0).unwrap(), row.get_long(1).unwrap())
+            })
+            .collect();
+        assert_eq!(metadata.num_rows(), rows.len() as i64);
+        fs::remove_file(&path).unwrap();
+        (columns, rows)
+    }
+
+    #[test]
+    fn written_histogram_reads_back_with_its_rows_and_schema() {
+        let state = State::from([(40, 3), (7, 1), (1000, 12), (2, u64::MAX)]);
+        let (columns, rows) = write_and_read(&state, "histogram.parquet");
+        assert_eq!(
+            columns,
+            [
+                (
+                    "length".to_string(),
+                    PhysicalType::INT64,
+                    Repetition::REQUIRED
+                ),
+                (
+                    "count".to_string(),
+                    PhysicalType::INT64,
+                    Repetition::REQUIRED
+                )
+            ]
+        );
+        assert_eq!(rows, [(2, i64::MAX), (7, 1), (40, 3), (1000, 12)]);
+    }
+
+    #[test]
+    fn empty_histogram_has_no_rows() {
+        let (columns, rows) = write_and_read(&State::new(), "empty.parquet");
+        assert_eq!(columns.len(), 2);
+        assert!(rows.is_empty());
+    }
+}
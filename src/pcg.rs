@@ -0,0 +1,82 @@
+use rand::{Error, RngCore};
+
+/// Stream of [`Pcg32`] used when only a seed is given, the one of the reference implementation
+const DEFAULT_STREAM: u64 = 0xDA3E_39CB_94B9_5BDB;
+
+/// The PCG32 generator (XSH RR variant) as described by O'Neill
+///
+/// Each output advances a 64 bit linear congruential state and permutes the previous one:
+/// ```text
+/// state = state * 6364136223846793005 + increment
+/// xorshifted = ((old ^ (old >> 18)) >> 27) as u32
+/// xorshifted.rotate_right(old >> 59)
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    /// Generator started like `pcg32_srandom_r(seed, stream)` of the reference implementation
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+        rng
+    }
+
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, DEFAULT_STREAM)
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(self.increment);
+    }
+}
+
+impl RngCore for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = ((old ^ (old >> 18)) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = self.next_u32() as u64;
+        ((self.next_u32() as u64) << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_reference_outputs() {
+        let mut rng = Pcg32::with_stream(42, 54);
+        assert_eq!(rng.next_u32(), 0xA15C_02B7);
+        assert_eq!(rng.next_u32(), 0x7B47_F409);
+        assert_eq!(rng.next_u32(), 0xBA1D_3330);
+    }
+}
@@ -0,0 +1,1518 @@
+#[cfg(not(feature = "rayon"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    ops::Range,
+    sync::Mutex,
+};
+
+use clap::ValueEnum;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    game::{deck_disorder, format_cards, Deal, Event, Face, Game, GameRules, Stats},
+    merge_states, GowsimError, State,
+};
+
+/// Default of [`Simulation::batch_size`]
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Metric {
+    /// Number of turns until one player holds all cards
+    Length,
+    /// Number of times the lead switched between players
+    LeadChanges,
+    /// Number of wars, not counting nested ones
+    WarCount,
+    /// Number of war rounds, counting nested wars as well
+    WarRounds,
+    /// Number of times either player turned over their winnings pile to draw from it
+    PileRecycles,
+    /// Most cards the eventual loser ever held, as the winner always ends up holding all cards
+    LoserMaxPile,
+    /// Turn of the first war, 0 for games without any war
+    FirstWarTurn,
+}
+
+impl Metric {
+    pub fn measure(&self, game: &Game) -> usize {
+        let stats = &game.stats;
+        match *self {
+            Metric::Length => stats.turn_number,
+            Metric::LeadChanges => stats.lead_changes,
+            Metric::WarCount => stats.war_count,
+            Metric::WarRounds => stats.war_rounds,
+            Metric::PileRecycles => game.players.0.recycle_count + game.players.1.recycle_count,
+            Metric::LoserMaxPile => match game.winner() {
+                Some(0) => stats.max_pile.1,
+                Some(_) => stats.max_pile.0,
+                None => stats.max_pile.0.min(stats.max_pile.1),
+            },
+            Metric::FirstWarTurn => stats.first_war_turn.unwrap_or(0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Metric::Length => "length",
+            Metric::LeadChanges => "lead_changes",
+            Metric::WarCount => "war_count",
+            Metric::WarRounds => "war_rounds",
+            Metric::PileRecycles => "pile_recycles",
+            Metric::LoserMaxPile => "loser_max_pile",
+            Metric::FirstWarTurn => "first_war_turn",
+        }
+    }
+}
+
+/// Collects results from the games of a [`Simulation`]
+///
+/// Every worker thread records a share of the games into a recorder of its own. Once all games
+/// are played the recorders are merged in seed order.
+pub trait Recorder: Send {
+    /// Called before the first turn of every game
+    fn start_game(&mut self, _seed: u64, _game: &Game) {}
+    /// Called after every turn with the events it produced
+    fn record_turn(&mut self, _game: &Game, _events: &[Event]) {}
+    /// Checked after every turn, returning `true` ends the current game early
+    fn should_stop(&self) -> bool {
+        false
+    }
+    /// Called once the game is over or was stopped
+    fn finish_game(&mut self, _seed: u64, _game: &Game) {}
+    /// Adds the results of `other`, which recorded the games following the ones of `self`
+    fn merge(&mut self, other: Self);
+}
+
+/// Histogram of a [`Metric`] over all games, along with totals over all games
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    pub metric: Metric,
+    pub histogram: State,
+    pub total_games: usize,
+    /// Stats of all games merged with [`Stats::merge`]
+    pub totals: Stats,
+    /// Games without a winner because they were stopped early
+    pub draws: usize,
+}
+
+impl SimulationResult {
+    pub fn new(metric: Metric) -> Self {
+        SimulationResult {
+            metric,
+            histogram: State::new(),
+            total_games: 0,
+            totals: Stats::default(),
+            draws: 0,
+        }
+    }
+
+    pub fn mean_turns(&self) -> f64 {
+        self.totals.turn_number as f64 / self.total_games as f64
+    }
+}
+
+impl Recorder for SimulationResult {
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        *self.histogram.entry(self.metric.measure(game)).or_insert(0) += 1;
+        self.total_games += 1;
+        self.totals.merge(&game.stats);
+        if game.winner().is_none() {
+            self.draws += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        merge_states(&mut self.histogram, other.histogram);
+        self.total_games += other.total_games;
+        self.totals.merge(&other.totals);
+        self.draws += other.draws;
+    }
+}
+
+/// Histogram of the number of cards won by every war
+#[derive(Debug, Clone, Default)]
+pub struct WarPotSizes {
+    pub state: State,
+}
+
+impl Recorder for WarPotSizes {
+    fn record_turn(&mut self, _game: &Game, events: &[Event]) {
+        for event in events {
+            if let Event::WarEnd { pot_size, .. } = event {
+                *self.state.entry(*pot_size).or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        merge_states(&mut self.state, other.state);
+    }
+}
+
+/// Histogram of the strength of the card winning the last battle or war of every decided game
+#[derive(Debug, Clone, Default)]
+pub struct FinishingRank {
+    pub state: State,
+    last_winning_face: Option<Face>,
+}
+
+impl Recorder for FinishingRank {
+    fn start_game(&mut self, _seed: u64, _game: &Game) {
+        self.last_winning_face = None;
+    }
+
+    fn record_turn(&mut self, _game: &Game, events: &[Event]) {
+        // The outermost war of a turn ends last
+        let winning_face = events.iter().rev().find_map(|event| match event {
+            Event::ShortBattle { winning_card, .. } => Some(winning_card.face()),
+            Event::WarEnd {
+                winning_player_id,
+                final_top_cards,
+                ..
+            } => Some(if *winning_player_id == 0 {
+                final_top_cards.0.face()
+            } else {
+                final_top_cards.1.face()
+            }),
+            _ => None,
+        });
+        if winning_face.is_some() {
+            self.last_winning_face = winning_face;
+        }
+    }
+
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        if let (Some(face), Some(_)) = (self.last_winning_face, game.winner()) {
+            *self.state.entry(face.measure_strength()).or_insert(0) += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        merge_states(&mut self.state, other.state);
+    }
+}
+
+/// Detects games returning to a previously seen state and stops them
+///
+/// Every turn the [`Game::state_hash`] is remembered, up to `max_states` per game to bound
+/// the memory used. With randomness during play a repeated state is only a coincidence, under
+/// deterministic rules it means the game loops forever.
+#[derive(Debug, Clone)]
+pub struct CycleDetection {
+    pub max_states: usize,
+    /// Histogram of the turn at which a game first repeated a state
+    pub state: State,
+    seen: HashSet<u64>,
+    cycle_found: bool,
+}
+
+impl CycleDetection {
+    pub fn new(max_states: usize) -> Self {
+        CycleDetection {
+            max_states,
+            state: State::new(),
+            seen: HashSet::new(),
+            cycle_found: false,
+        }
+    }
+
+    /// Number of games that repeated a state
+    pub fn games_with_cycle(&self) -> u64 {
+        self.state.values().sum()
+    }
+}
+
+impl Recorder for CycleDetection {
+    fn start_game(&mut self, _seed: u64, game: &Game) {
+        self.seen.clear();
+        self.seen.insert(game.state_hash());
+        self.cycle_found = false;
+    }
+
+    fn record_turn(&mut self, game: &Game, _events: &[Event]) {
+        let hash = game.state_hash();
+        if self.seen.contains(&hash) {
+            self.cycle_found = true;
+            *self.state.entry(game.stats.turn_number).or_insert(0) += 1;
+        } else if self.seen.len() < self.max_states {
+            self.seen.insert(hash);
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.cycle_found
+    }
+
+    fn merge(&mut self, other: Self) {
+        merge_states(&mut self.state, other.state);
+    }
+}
+
+/// Counts how games ended: with a winner, stuck in a loop found by [`CycleDetection`], or
+/// stopped at [`Simulation::max_turns`]
+#[derive(Debug, Clone)]
+pub struct Termination {
+    pub finished: usize,
+    pub looped: usize,
+    pub capped: usize,
+    cycles: CycleDetection,
+}
+
+impl Termination {
+    pub fn new(max_states: usize) -> Self {
+        Termination {
+            finished: 0,
+            looped: 0,
+            capped: 0,
+            cycles: CycleDetection::new(max_states),
+        }
+    }
+
+    /// Fraction of all games that ended with a winner
+    pub fn finished_rate(&self) -> f64 {
+        self.finished as f64 / (self.finished + self.looped + self.capped) as f64
+    }
+}
+
+impl Recorder for Termination {
+    fn start_game(&mut self, seed: u64, game: &Game) {
+        self.cycles.start_game(seed, game);
+    }
+
+    fn record_turn(&mut self, game: &Game, events: &[Event]) {
+        self.cycles.record_turn(game, events);
+    }
+
+    fn should_stop(&self) -> bool {
+        self.cycles.should_stop()
+    }
+
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        if game.winner().is_some() {
+            self.finished += 1;
+        } else if self.cycles.cycle_found {
+            self.looped += 1;
+        } else {
+            self.capped += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.finished += other.finished;
+        self.looped += other.looped;
+        self.capped += other.capped;
+    }
+}
+
+/// Counts games dragging on in an endgame, spending more than `turns` turns with one player
+/// holding fewer than `cards` cards
+///
+/// As all cards stay in the game only the trailing player can be low on cards, so this is the
+/// endgame where a player keeps winning back just enough cards to stay alive.
+#[derive(Debug, Clone)]
+pub struct DrawnOutEndgame {
+    pub cards: usize,
+    pub turns: usize,
+    /// Number of games exceeding `turns` turns in the endgame
+    pub drawn_out: usize,
+    pub games: usize,
+    turns_in_endgame: usize,
+}
+
+impl DrawnOutEndgame {
+    pub fn new(cards: usize, turns: usize) -> Self {
+        DrawnOutEndgame {
+            cards,
+            turns,
+            drawn_out: 0,
+            games: 0,
+            turns_in_endgame: 0,
+        }
+    }
+}
+
+impl Recorder for DrawnOutEndgame {
+    fn start_game(&mut self, _seed: u64, _game: &Game) {
+        self.turns_in_endgame = 0;
+    }
+
+    fn record_turn(&mut self, game: &Game, _events: &[Event]) {
+        let fewest = game
+            .players
+            .0
+            .count_cards()
+            .min(game.players.1.count_cards());
+        if fewest < self.cards {
+            self.turns_in_endgame += 1;
+        }
+    }
+
+    fn finish_game(&mut self, _seed: u64, _game: &Game) {
+        self.games += 1;
+        if self.turns_in_endgame > self.turns {
+            self.drawn_out += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.drawn_out += other.drawn_out;
+        self.games += other.games;
+    }
+}
+
+/// Histograms of every [`Metric`] at once
+#[derive(Debug, Clone)]
+pub struct AllMetrics {
+    pub states: Vec<(Metric, State)>,
+}
+
+impl Default for AllMetrics {
+    fn default() -> Self {
+        AllMetrics {
+            states: Metric::value_variants()
+                .iter()
+                .map(|metric| (*metric, State::new()))
+                .collect(),
+        }
+    }
+}
+
+impl Recorder for AllMetrics {
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        for (metric, state) in &mut self.states {
+            *state.entry(metric.measure(game)).or_insert(0) += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for ((_, state), (_, other)) in self.states.iter_mut().zip(other.states) {
+            merge_states(state, other);
+        }
+    }
+}
+
+/// Histogram of pairs of metrics, counting the games per combination of their values
+#[derive(Debug, Clone)]
+pub struct JointHistogram {
+    pub metrics: (Metric, Metric),
+    pub state: HashMap<(usize, usize), u64>,
+}
+
+impl JointHistogram {
+    pub fn new(x: Metric, y: Metric) -> Self {
+        JointHistogram {
+            metrics: (x, y),
+            state: HashMap::new(),
+        }
+    }
+}
+
+impl Recorder for JointHistogram {
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        let values = (self.metrics.0.measure(game), self.metrics.1.measure(game));
+        *self.state.entry(values).or_insert(0) += 1;
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (values, count) in other.state {
+            let total = self.state.entry(values).or_insert(0);
+            *total = total.saturating_add(count);
+        }
+    }
+}
+
+/// Counts finished games won by a player who was once behind by more than `threshold` cards
+#[derive(Debug, Clone)]
+pub struct Comeback {
+    pub threshold: usize,
+    pub comebacks: usize,
+    /// Number of games with a winner
+    pub games: usize,
+    /// Largest lead of player 0 and player 1 in the current game
+    max_lead: [usize; 2],
+}
+
+impl Comeback {
+    pub fn new(threshold: usize) -> Self {
+        Comeback {
+            threshold,
+            comebacks: 0,
+            games: 0,
+            max_lead: [0; 2],
+        }
+    }
+
+    /// Fraction of the games with a winner that were comebacks
+    pub fn comeback_rate(&self) -> f64 {
+        self.comebacks as f64 / self.games as f64
+    }
+}
+
+impl Recorder for Comeback {
+    fn start_game(&mut self, _seed: u64, _game: &Game) {
+        self.max_lead = [0; 2];
+    }
+
+    fn record_turn(&mut self, game: &Game, _events: &[Event]) {
+        let cards = [game.players.0.count_cards(), game.players.1.count_cards()];
+        for player_id in 0..2 {
+            let lead = cards[player_id].saturating_sub(cards[1 - player_id]);
+            self.max_lead[player_id] = self.max_lead[player_id].max(lead);
+        }
+    }
+
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        if let Some(winner) = game.winner() {
+            self.games += 1;
+            if self.max_lead[1 - winner] > self.threshold {
+                self.comebacks += 1;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.comebacks += other.comebacks;
+        self.games += other.games;
+    }
+}
+
+/// Histogram of the number of cards the loser held before the last turn of every decided game,
+/// telling close games apart from blowouts
+#[derive(Debug, Clone, Default)]
+pub struct Closeness {
+    pub state: State,
+    /// Cards held by player 0 and player 1 before and after the latest turn
+    before_last: [usize; 2],
+    last: [usize; 2],
+}
+
+impl Recorder for Closeness {
+    fn start_game(&mut self, _seed: u64, game: &Game) {
+        self.last = [game.players.0.count_cards(), game.players.1.count_cards()];
+        self.before_last = self.last;
+    }
+
+    fn record_turn(&mut self, game: &Game, _events: &[Event]) {
+        self.before_last = self.last;
+        self.last = [game.players.0.count_cards(), game.players.1.count_cards()];
+    }
+
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        if let Some(winner) = game.winner() {
+            *self.state.entry(self.before_last[1 - winner]).or_insert(0) += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        merge_states(&mut self.state, other.state);
+    }
+}
+
+/// Finds the seed of the longest game, ignoring games stopped before they were over
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LongestGame {
+    /// Seed and turn count of the longest finished game, the lowest seed wins ties
+    pub longest: Option<(u64, usize)>,
+    /// Number of games stopped before one player held all cards
+    pub unfinished: usize,
+}
+
+impl LongestGame {
+    fn consider(&mut self, seed: u64, turns: usize) {
+        let is_longer = match self.longest {
+            Some((longest_seed, longest_turns)) => {
+                turns > longest_turns || (turns == longest_turns && seed < longest_seed)
+            }
+            None => true,
+        };
+        if is_longer {
+            self.longest = Some((seed, turns));
+        }
+    }
+}
+
+impl Recorder for LongestGame {
+    fn finish_game(&mut self, seed: u64, game: &Game) {
+        if game.winner().is_some() {
+            self.consider(seed, game.stats.turn_number);
+        } else {
+            self.unfinished += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        if let Some((seed, turns)) = other.longest {
+            self.consider(seed, turns);
+        }
+        self.unfinished += other.unfinished;
+    }
+}
+
+/// Number of games won by each player
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WinCount {
+    pub wins: [usize; 2],
+}
+
+impl WinCount {
+    /// Fraction of the decided games won by `player_id`
+    pub fn win_rate(&self, player_id: usize) -> f64 {
+        self.wins[player_id] as f64 / (self.wins[0] + self.wins[1]) as f64
+    }
+}
+
+impl Recorder for WinCount {
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        if let Some(winner) = game.winner() {
+            self.wins[winner] += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.wins[0] += other.wins[0];
+        self.wins[1] += other.wins[1];
+    }
+}
+
+/// Contingency table of the winner of the first turn, by battle or war, and the winner of the game
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FirstTurnWinner {
+    /// Number of decided games indexed by the first turn's winner, then the game's winner
+    pub games: [[usize; 2]; 2],
+    first_turn_winner: Option<usize>,
+}
+
+impl FirstTurnWinner {
+    /// Fraction of the decided games won by the player who won the first turn
+    pub fn first_turn_win_rate(&self) -> f64 {
+        let kept = self.games[0][0] + self.games[1][1];
+        kept as f64 / self.games.iter().flatten().sum::<usize>() as f64
+    }
+}
+
+impl Recorder for FirstTurnWinner {
+    fn start_game(&mut self, _seed: u64, _game: &Game) {
+        self.first_turn_winner = None;
+    }
+
+    fn record_turn(&mut self, game: &Game, events: &[Event]) {
+        if game.stats.turn_number != 1 {
+            return;
+        }
+        // The outermost war of a turn ends last
+        self.first_turn_winner = events.iter().rev().find_map(|event| match event {
+            Event::ShortBattle {
+                winning_player_id, ..
+            }
+            | Event::WarEnd {
+                winning_player_id, ..
+            } => Some(*winning_player_id),
+            _ => None,
+        });
+    }
+
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        if let (Some(first), Some(winner)) = (self.first_turn_winner, game.winner()) {
+            self.games[first][winner] += 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (row, other_row) in self.games.iter_mut().zip(other.games) {
+            for (count, other_count) in row.iter_mut().zip(other_row) {
+                *count += other_count;
+            }
+        }
+    }
+}
+
+/// Records every game into both recorders
+impl<A: Recorder, B: Recorder> Recorder for (A, B) {
+    fn start_game(&mut self, seed: u64, game: &Game) {
+        self.0.start_game(seed, game);
+        self.1.start_game(seed, game);
+    }
+
+    fn record_turn(&mut self, game: &Game, events: &[Event]) {
+        self.0.record_turn(game, events);
+        self.1.record_turn(game, events);
+    }
+
+    fn should_stop(&self) -> bool {
+        self.0.should_stop() || self.1.should_stop()
+    }
+
+    fn finish_game(&mut self, seed: u64, game: &Game) {
+        self.0.finish_game(seed, game);
+        self.1.finish_game(seed, game);
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.0.merge(other.0);
+        self.1.merge(other.1);
+    }
+}
+
+/// Sums the time spent in battles and wars over all games
+#[cfg(feature = "profile")]
+impl Recorder for crate::game::Profile {
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        self.battle_nanos += game.stats.profile.battle_nanos;
+        self.war_nanos += game.stats.profile.war_nanos;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.battle_nanos += other.battle_nanos;
+        self.war_nanos += other.war_nanos;
+    }
+}
+
+/// Log of the initial deal of every game, one `seed,player0,player1` CSV line per game
+///
+/// The piles are listed bottom to top as space separated cards. Interleaving them, starting
+/// with player 0, gives the deck to replay the game with [`Game::from_deck`] and the same seed,
+/// unless [`GameRules::handicap`], [`GameRules::deal_chunk`] or [`GameRules::first_war`]
+/// rearranged the piles or [`GameRules::burn_count`] took cards out of play.
+///
+/// The whole log is kept in memory, at roughly 160 bytes per game.
+#[derive(Debug, Clone, Default)]
+pub struct DealLog {
+    pub lines: String,
+}
+
+impl Recorder for DealLog {
+    fn start_game(&mut self, seed: u64, game: &Game) {
+        writeln!(
+            self.lines,
+            "{},{},{}",
+            seed,
+            format_cards(&game.players.0.draw_pile),
+            format_cards(&game.players.1.draw_pile)
+        )
+        .unwrap();
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.lines.push_str(&other.lines);
+    }
+}
+
+/// Final [`Stats`] of every game in seed order
+///
+/// All of them are kept in memory, at 64 bytes per game, so prefer a histogram for
+/// long runs.
+#[derive(Debug, Clone, Default)]
+pub struct StatsLog {
+    pub stats: Vec<Stats>,
+}
+
+impl Recorder for StatsLog {
+    fn finish_game(&mut self, _seed: u64, game: &Game) {
+        self.stats.push(game.stats.clone());
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.stats.extend(other.stats);
+    }
+}
+
+/// Log of how disordered the deal of every game was and how long the game took, one
+/// `seed,disorder,length` CSV line per game
+///
+/// The disorder is [`deck_disorder`] of the initial piles read alternately, starting with
+/// player 0, which is the dealt deck unless [`GameRules::handicap`], [`GameRules::deal_chunk`]
+/// or [`GameRules::first_war`] rearranged it or [`GameRules::burn_count`] took cards out of
+/// play.
+#[derive(Debug, Clone, Default)]
+pub struct DisorderLog {
+    pub lines: String,
+    disorder: usize,
+}
+
+impl Recorder for DisorderLog {
+    fn start_game(&mut self, _seed: u64, game: &Game) {
+        let (player0, player1) = &game.players;
+        let mut deck = Vec::with_capacity(player0.draw_pile.len() + player1.draw_pile.len());
+        for i in 0..player0.draw_pile.len().max(player1.draw_pile.len()) {
+            deck.extend(player0.draw_pile.get(i).cloned());
+            deck.extend(player1.draw_pile.get(i).cloned());
+        }
+        self.disorder = deck_disorder(&deck);
+    }
+
+    fn finish_game(&mut self, seed: u64, game: &Game) {
+        writeln!(
+            self.lines,
+            "{},{},{}",
+            seed, self.disorder, game.stats.turn_number
+        )
+        .unwrap();
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.lines.push_str(&other.lines);
+    }
+}
+
+/// A batch of games whose results are collected into a single histogram
+///
+/// Game `i` of the batch is played with the seed `base_seed + i`. The results only depend on
+/// this set of seeds and the rules, never on the number of threads or the order in which the
+/// games get scheduled.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub num_games: usize,
+    pub base_seed: u64,
+    /// Number of worker threads, 0 uses one per core
+    pub threads: usize,
+    /// Number of games each task plays before reporting progress, 0 counts as 1
+    pub batch_size: usize,
+    pub rules: GameRules,
+    pub metric: Metric,
+    /// Games still running after this many turns are stopped
+    pub max_turns: Option<usize>,
+    pub deal: Deal,
+}
+
+impl Simulation {
+    pub fn new(num_games: usize, base_seed: u64) -> Self {
+        Simulation {
+            num_games,
+            base_seed,
+            threads: 1,
+            batch_size: DEFAULT_BATCH_SIZE,
+            rules: GameRules::default(),
+            metric: Metric::Length,
+            max_turns: None,
+            deal: Deal::Shuffled,
+        }
+    }
+
+    /// Batch playing every seed in `seeds` once
+    pub fn from_seed_range(seeds: Range<u64>) -> Self {
+        Self::new(
+            (seeds.end.saturating_sub(seeds.start)) as usize,
+            seeds.start,
+        )
+    }
+
+    /// Plays all games and returns the histogram of the chosen metric
+    ///
+    /// `on_progress` is called with the number of finished games every [`Simulation::batch_size`]
+    /// games.
+    /// Calls never overlap and always observe increasing counts.
+    pub fn run(&self, on_progress: impl Fn(usize) + Sync) -> Result<SimulationResult, GowsimError> {
+        self.run_with(|| SimulationResult::new(self.metric), on_progress)
+    }
+
+    /// Plays all games, recording them into recorders created by `new_recorder`
+    ///
+    /// Fails without playing any game if [`GameRules::validate`] rejects the rules.
+    pub fn run_with<R: Recorder>(
+        &self,
+        new_recorder: impl Fn() -> R + Sync,
+        on_progress: impl Fn(usize) + Sync,
+    ) -> Result<R, GowsimError> {
+        self.rules.validate()?;
+        let games_done = Mutex::new(0);
+        let run_batch = |first: usize| {
+            let last = (first + self.effective_batch_size()).min(self.num_games);
+            let mut recorder = new_recorder();
+            self.run_range(first, last, &mut recorder);
+            let mut games_done = games_done.lock().unwrap();
+            *games_done += last - first;
+            on_progress(*games_done);
+            recorder
+        };
+        Ok(self.run_batches(run_batch).unwrap_or_else(&new_recorder))
+    }
+
+    /// [`Simulation::batch_size`], treating 0 as 1 so that batches always make progress
+    fn effective_batch_size(&self) -> usize {
+        self.batch_size.max(1)
+    }
+
+    /// Runs the batch starting at every multiple of [`Simulation::batch_size`] on a rayon pool,
+    /// merging their recorders in seed order
+    #[cfg(feature = "rayon")]
+    fn run_batches<R: Recorder>(&self, run_batch: impl Fn(usize) -> R + Sync) -> Option<R> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            (0..self.num_games)
+                .into_par_iter()
+                .step_by(self.effective_batch_size())
+                .map(|first| Some(run_batch(first)))
+                .reduce(
+                    || None,
+                    |a, b| match (a, b) {
+                        (Some(mut a), Some(b)) => {
+                            a.merge(b);
+                            Some(a)
+                        }
+                        (a, b) => a.or(b),
+                    },
+                )
+        })
+    }
+
+    /// Same as the rayon backend, but the batches are handed out to scoped std threads
+    #[cfg(not(feature = "rayon"))]
+    fn run_batches<R: Recorder>(&self, run_batch: impl Fn(usize) -> R + Sync) -> Option<R> {
+        let threads = match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        };
+        let next_batch = AtomicUsize::new(0);
+        let mut batches: Vec<(usize, R)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut batches = Vec::new();
+                        loop {
+                            let first = next_batch
+                                .fetch_add(self.effective_batch_size(), Ordering::Relaxed);
+                            if first >= self.num_games {
+                                return batches;
+                            }
+                            batches.push((first, run_batch(first)));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        batches.sort_unstable_by_key(|(first, _)| *first);
+        batches
+            .into_iter()
+            .map(|(_, recorder)| recorder)
+            .reduce(|mut a, b| {
+                a.merge(b);
+                a
+            })
+    }
+
+    fn run_range(&self, first: usize, last: usize, recorder: &mut impl Recorder) {
+        // One game and event buffer per range, reset for every seed so they are only allocated once
+        let mut game = Game::with_rules(self.rules.clone());
+        let mut events = Vec::new();
+        for i in first..last {
+            let seed = self.base_seed.wrapping_add(i as u64);
+            game.reset_dealt(seed, self.deal);
+            recorder.start_game(seed, &game);
+            while game.step_into(&mut events) {
+                recorder.record_turn(&game, &events);
+                if recorder.should_stop()
+                    || self
+                        .max_turns
+                        .is_some_and(|max_turns| game.stats.turn_number >= max_turns)
+                {
+                    break;
+                }
+            }
+            recorder.finish_game(seed, &game);
+        }
+    }
+}
+
+/// Histogram of game lengths for `num_games` games with the standard rules
+pub fn simulate_lengths(num_games: usize, base_seed: u64) -> SimulationResult {
+    Simulation::new(num_games, base_seed)
+        .run(|_| {})
+        .expect("The standard rules are valid")
+}
+
+/// Final [`Stats`] of `num_games` games with the standard rules, see [`StatsLog`] for the
+/// memory used
+pub fn simulate_collect_stats(num_games: usize, base_seed: u64) -> Vec<Stats> {
+    Simulation::new(num_games, base_seed)
+        .run_with(StatsLog::default, |_| {})
+        .expect("The standard rules are valid")
+        .stats
+}
+
+/// Same as [`simulate_lengths`], but spread over `threads` threads, reporting the number of
+/// finished games to `on_progress` as the run goes
+pub fn simulate_lengths_parallel(
+    num_games: usize,
+    base_seed: u64,
+    threads: usize,
+    on_progress: impl Fn(usize) + Sync,
+) -> SimulationResult {
+    Simulation {
+        threads,
+        ..Simulation::new(num_games, base_seed)
+    }
+    .run(on_progress)
+    .expect("The standard rules are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Deck, PotShuffle, RngAlgorithm};
+
+    #[test]
+    fn progress_counts_increase_up_to_the_number_of_games() {
+        let counts = Mutex::new(Vec::new());
+        let simulation = Simulation {
+            threads: 4,
+            batch_size: 10,
+            ..Simulation::new(1000, 0)
+        };
+        let result = simulation
+            .run(|games_done| counts.lock().unwrap().push(games_done))
+            .unwrap();
+        assert_eq!(result.total_games, 1000);
+        let counts = counts.into_inner().unwrap();
+        assert_eq!(counts.len(), 100);
+        assert!(counts.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(counts.last(), Some(&1000));
+    }
+
+    #[test]
+    fn parallel_lengths_report_progress_and_match_the_serial_ones() {
+        let counts = Mutex::new(Vec::new());
+        let result = simulate_lengths_parallel(100, 3, 4, |games_done| {
+            counts.lock().unwrap().push(games_done)
+        });
+        assert_eq!(counts.into_inner().unwrap(), [100]);
+        assert_eq!(result.histogram, simulate_lengths(100, 3).histogram);
+    }
+
+    #[test]
+    fn logged_deals_replay_the_same_games() {
+        let (deals, stats) = Simulation::new(20, 11)
+            .run_with(|| (DealLog::default(), StatsLog::default()), |_| {})
+            .unwrap();
+        let lines: Vec<&str> = deals.lines.lines().collect();
+        assert_eq!(lines.len(), 20);
+        for (line, stats) in lines.into_iter().zip(stats.stats) {
+            let mut fields = line.split(',');
+            let seed: u64 = fields.next().unwrap().parse().unwrap();
+            let player0: Deck = fields.next().unwrap().parse().unwrap();
+            let player1: Deck = fields.next().unwrap().parse().unwrap();
+            let deck: Vec<_> = player0
+                .cards()
+                .iter()
+                .zip(player1.cards())
+                .flat_map(|(a, b)| [a.clone(), b.clone()])
+                .collect();
+            let mut game = Game::from_deck(deck.into(), seed, GameRules::default());
+            game.play_to_completion();
+            assert_eq!(game.stats.turn_number, stats.turn_number);
+        }
+    }
+
+    #[test]
+    fn invalid_rules_fail_the_run_before_any_game() {
+        let simulation = Simulation {
+            rules: GameRules {
+                deal_chunk: 0,
+                ..GameRules::default()
+            },
+            ..Simulation::new(10, 0)
+        };
+        let progress = Mutex::new(0);
+        let result = simulation.run(|_| *progress.lock().unwrap() += 1);
+        assert!(matches!(result, Err(GowsimError::InvalidConfig(_))));
+        assert_eq!(progress.into_inner().unwrap(), 0);
+    }
+
+    fn player0_win_rate(rules: GameRules) -> f64 {
+        Simulation {
+            rules,
+            ..Simulation::new(1000, 0)
+        }
+        .run_with(WinCount::default, |_| {})
+        .unwrap()
+        .win_rate(0)
+    }
+
+    #[test]
+    fn handicap_favours_player_0() {
+        let handicap = |handicap| GameRules {
+            handicap,
+            ..GameRules::default()
+        };
+        let game = Game::seeded(0, handicap(16));
+        assert_eq!(game.players.0.count_cards(), 42);
+        assert_eq!(game.players.1.count_cards(), 10);
+        let even = player0_win_rate(handicap(0));
+        assert!((0.45..0.55).contains(&even), "{}", even);
+        let handicapped = player0_win_rate(handicap(16));
+        assert!(handicapped > 0.8, "{}", handicapped);
+    }
+
+    #[test]
+    fn seed_range_gives_the_same_histogram_on_any_number_of_threads() {
+        let histogram = |threads| {
+            Simulation {
+                threads,
+                batch_size: 7,
+                ..Simulation::from_seed_range(1000..1300)
+            }
+            .run(|_| {})
+            .unwrap()
+            .histogram
+        };
+        assert_eq!(histogram(1), histogram(4));
+    }
+
+    #[test]
+    fn cycle_detection_stops_a_looping_deal() {
+        let rules = GameRules {
+            pot_shuffle: PotShuffle::WinnerFirst,
+            ..GameRules::default()
+        };
+        // Without shuffled pots these five cards are passed back and forth forever
+        let deck: Deck = "2H 3S 4D 6H 5C".parse().unwrap();
+        let mut game = Game::from_deck(deck.clone(), 0, rules.clone());
+        for _ in 0..10_000 {
+            game.step();
+        }
+        assert_eq!(game.winner(), None);
+
+        let mut game = Game::from_deck(deck, 0, rules);
+        let mut cycles = CycleDetection::new(100);
+        cycles.start_game(0, &game);
+        let mut events = Vec::new();
+        while !cycles.should_stop() && game.step_into(&mut events) {
+            cycles.record_turn(&game, &events);
+        }
+        cycles.finish_game(0, &game);
+        assert_eq!(cycles.state, State::from([(11, 1)]));
+        assert_eq!(cycles.games_with_cycle(), 1);
+    }
+
+    #[test]
+    fn longest_game_is_found_on_any_number_of_threads() {
+        let simulation = Simulation {
+            batch_size: 9,
+            ..Simulation::from_seed_range(500..600)
+        };
+        let stats = simulation.run_with(StatsLog::default, |_| {}).unwrap();
+        let expected = stats
+            .stats
+            .iter()
+            .zip(500..)
+            .map(|(stats, seed)| (seed, stats.turn_number))
+            .reduce(|a, b| if b.1 > a.1 { b } else { a });
+        for threads in [1, 4] {
+            let longest = Simulation {
+                threads,
+                ..simulation.clone()
+            }
+            .run_with(LongestGame::default, |_| {})
+            .unwrap();
+            assert_eq!(longest.longest, expected);
+            assert_eq!(longest.unfinished, 0);
+        }
+    }
+
+    #[test]
+    fn longest_game_ties_go_to_the_lowest_seed() {
+        let mut longest = LongestGame::default();
+        longest.consider(8, 300);
+        longest.consider(3, 300);
+        longest.consider(5, 300);
+        assert_eq!(longest.longest, Some((3, 300)));
+    }
+
+    #[test]
+    fn result_totals_match_the_histogram() {
+        let result = simulate_lengths(200, 5);
+        assert_eq!(result.total_games, 200);
+        assert_eq!(result.histogram.values().sum::<u64>(), 200);
+        let turns: u64 = result
+            .histogram
+            .iter()
+            .map(|(length, count)| *length as u64 * count)
+            .sum();
+        assert_eq!(result.totals.turn_number as u64, turns);
+        let wars = simulate_collect_stats(200, 5)
+            .iter()
+            .map(|stats| stats.war_count)
+            .sum::<usize>();
+        assert_eq!(result.totals.war_count, wars);
+        assert_eq!(result.draws, 0);
+    }
+
+    #[test]
+    fn games_stopped_early_count_as_draws() {
+        let result = Simulation {
+            max_turns: Some(50),
+            ..Simulation::new(200, 5)
+        }
+        .run(|_| {})
+        .unwrap();
+        let stopped = result.histogram.get(&50).copied().unwrap_or(0) as usize;
+        assert!(stopped > 0);
+        assert!(result.histogram.keys().all(|length| *length <= 50));
+        // Games ending on exactly the last allowed turn have a winner nonetheless
+        assert!(result.draws <= stopped);
+        assert!(result.draws > 0);
+    }
+
+    #[test]
+    fn first_turn_winner_is_recorded_for_battles_and_wars() {
+        for rules in [
+            GameRules::default(),
+            GameRules {
+                first_war: Some(Face::Queen),
+                ..GameRules::default()
+            },
+        ] {
+            let mut game = Game::seeded(6, rules.clone());
+            let first_turn = game.step().unwrap();
+            let was_war = first_turn
+                .iter()
+                .any(|event| matches!(event, Event::WarStart { .. }));
+            assert_eq!(was_war, rules.first_war.is_some());
+            let first = first_turn
+                .iter()
+                .rev()
+                .find_map(|event| match event {
+                    Event::ShortBattle {
+                        winning_player_id, ..
+                    }
+                    | Event::WarEnd {
+                        winning_player_id, ..
+                    } => Some(*winning_player_id),
+                    _ => None,
+                })
+                .unwrap();
+            game.play_to_completion();
+            let winner = game.winner().unwrap();
+            let recorded = Simulation {
+                rules,
+                ..Simulation::new(1, 6)
+            }
+            .run_with(FirstTurnWinner::default, |_| {})
+            .unwrap();
+            let mut expected = [[0; 2]; 2];
+            expected[first][winner] = 1;
+            assert_eq!(recorded.games, expected);
+        }
+    }
+
+    #[cfg(feature = "profile")]
+    #[test]
+    fn profile_times_battles_and_wars() {
+        let result = Simulation::new(20, 0)
+            .run_with(
+                || {
+                    (
+                        crate::game::Profile::default(),
+                        SimulationResult::new(Metric::Length),
+                    )
+                },
+                |_| {},
+            )
+            .unwrap();
+        let (profile, result) = result;
+        assert!(result.totals.war_count > 0);
+        assert!(profile.battle_nanos > 0);
+        assert!(profile.war_nanos > 0);
+        assert_eq!(profile.war_nanos, result.totals.profile.war_nanos);
+    }
+
+    #[test]
+    fn shuffled_pots_terminate_while_fixed_ones_can_loop() {
+        let termination = |pot_shuffle| {
+            Simulation {
+                rules: GameRules {
+                    pot_shuffle,
+                    include_trigger_cards: false,
+                    ..GameRules::default()
+                },
+                max_turns: Some(5000),
+                ..Simulation::from_seed_range(100..120)
+            }
+            .run_with(|| Termination::new(5000), |_| {})
+            .unwrap()
+        };
+        let shuffled = termination(PotShuffle::Random);
+        assert_eq!(shuffled.finished, 20);
+        assert_eq!(shuffled.finished_rate(), 1.0);
+        let fixed = termination(PotShuffle::WinnerFirst);
+        assert_eq!((fixed.finished, fixed.looped, fixed.capped), (20, 0, 0));
+
+        // Without shuffled pots these five cards are passed back and forth forever
+        let deck: Deck = "2H 3S 4D 6H 5C".parse().unwrap();
+        let mut termination = Termination::new(5000);
+        for pot_shuffle in [PotShuffle::Random, PotShuffle::WinnerFirst] {
+            let rules = GameRules {
+                pot_shuffle,
+                ..GameRules::default()
+            };
+            record_game(&mut termination, 0, Game::from_deck(deck.clone(), 0, rules));
+        }
+        assert_eq!(
+            (termination.finished, termination.looped, termination.capped),
+            (1, 1, 0)
+        );
+        assert_eq!(termination.finished_rate(), 0.5);
+    }
+
+    /// Plays `game` to completion into `recorder`, like a simulation playing it with `seed`
+    fn record_game(recorder: &mut impl Recorder, seed: u64, mut game: Game) {
+        recorder.start_game(seed, &game);
+        let mut events = Vec::new();
+        while !recorder.should_stop() && game.step_into(&mut events) {
+            recorder.record_turn(&game, &events);
+        }
+        recorder.finish_game(seed, &game);
+    }
+
+    #[test]
+    fn finishing_rank_is_the_card_deciding_the_last_turn() {
+        let mut ranks = FinishingRank::default();
+        // The king takes the two only cards
+        record_game(
+            &mut ranks,
+            0,
+            Game::from_deck("2H KS".parse().unwrap(), 0, GameRules::default()),
+        );
+        // The aces tie and the buried 3 wins the war and the game
+        record_game(
+            &mut ranks,
+            1,
+            Game::from_deck("3H 2S AH AS".parse().unwrap(), 1, GameRules::default()),
+        );
+        assert_eq!(ranks.state, State::from([(13, 1), (3, 1)]));
+
+        let mut ranks = FinishingRank::default();
+        let mut game = Game::seeded(8, GameRules::default());
+        record_game(&mut ranks, 8, game.clone());
+        let mut last_turn = Vec::new();
+        while let Some(events) = game.step() {
+            last_turn = events;
+        }
+        let winning_card = match &last_turn[..] {
+            [.., Event::ShortBattle { winning_card, .. }, Event::GameOver { .. }] => {
+                winning_card.clone()
+            }
+            [.., Event::WarEnd {
+                winning_player_id,
+                final_top_cards,
+                ..
+            }, Event::GameOver { .. }] => match winning_player_id {
+                0 => final_top_cards.0.clone(),
+                _ => final_top_cards.1.clone(),
+            },
+            events => panic!("Unexpected last turn {:?}", events),
+        };
+        assert_eq!(
+            ranks.state,
+            State::from([(winning_card.face().measure_strength(), 1)])
+        );
+    }
+
+    #[test]
+    fn batch_size_does_not_change_the_histogram() {
+        let histogram = |batch_size| {
+            Simulation {
+                threads: 3,
+                batch_size,
+                ..Simulation::from_seed_range(70..220)
+            }
+            .run(|_| {})
+            .unwrap()
+            .histogram
+        };
+        let expected = histogram(DEFAULT_BATCH_SIZE);
+        for batch_size in [0, 1, 7, 150, 1000] {
+            assert_eq!(histogram(batch_size), expected, "{}", batch_size);
+        }
+    }
+
+    #[test]
+    fn drawn_out_endgame_counts_the_turns_spent_low_on_cards() {
+        let rules = GameRules {
+            pot_shuffle: PotShuffle::WinnerFirst,
+            ..GameRules::default()
+        };
+        // Five cards passed back and forth forever, a player always holds two or less
+        let deck: Deck = "2H 3S 4D 6H 5C".parse().unwrap();
+        let mut short = DrawnOutEndgame::new(3, 40);
+        let mut long = DrawnOutEndgame::new(3, 60);
+        let mut game = Game::from_deck(deck, 0, rules);
+        short.start_game(0, &game);
+        long.start_game(0, &game);
+        let mut events = Vec::new();
+        while game.turn_number() < 50 && game.step_into(&mut events) {
+            short.record_turn(&game, &events);
+            long.record_turn(&game, &events);
+        }
+        short.finish_game(0, &game);
+        long.finish_game(0, &game);
+        assert_eq!((short.drawn_out, short.games), (1, 1));
+        assert_eq!((long.drawn_out, long.games), (0, 1));
+
+        let mut quick = DrawnOutEndgame::new(3, 0);
+        record_game(&mut quick, 0, Game::seeded(0, GameRules::default()));
+        let mut none = DrawnOutEndgame::new(1, 1);
+        record_game(&mut none, 0, Game::seeded(0, GameRules::default()));
+        // Only the last turn leaves the loser without any card
+        assert_eq!((quick.drawn_out, none.drawn_out), (1, 0));
+    }
+
+    #[test]
+    fn collected_stats_hold_one_game_per_seed() {
+        let stats = simulate_collect_stats(50, 9);
+        assert_eq!(stats.len(), 50);
+        assert!(stats.iter().all(|stats| stats.turn_number > 0));
+        let mut game = Game::seeded(9 + 17, GameRules::default());
+        game.play_to_completion();
+        assert_eq!(stats[17].turn_number, game.turn_number());
+        let mut histogram = State::new();
+        for stats in &stats {
+            *histogram.entry(stats.turn_number).or_default() += 1;
+        }
+        assert_eq!(histogram, simulate_lengths(50, 9).histogram);
+        assert!(simulate_collect_stats(0, 9).is_empty());
+    }
+
+    /// Compiled with and without the `rayon` feature this checks both backends against the same
+    /// games played one by one
+    #[test]
+    fn parallel_backend_matches_games_played_one_by_one() {
+        let mut expected = State::new();
+        let mut lengths = Vec::new();
+        for seed in 40..140 {
+            let mut game = Game::seeded(seed, GameRules::default());
+            game.play_to_completion();
+            *expected.entry(game.turn_number()).or_default() += 1;
+            lengths.push(game.turn_number());
+        }
+        for threads in [1, 4, 0] {
+            let simulation = Simulation {
+                threads,
+                batch_size: 7,
+                ..Simulation::new(100, 40)
+            };
+            assert_eq!(simulation.run(|_| {}).unwrap().histogram, expected);
+            let log = simulation.run_with(StatsLog::default, |_| {}).unwrap();
+            let logged: Vec<usize> = log.stats.iter().map(|stats| stats.turn_number).collect();
+            assert_eq!(logged, lengths);
+        }
+    }
+
+    #[test]
+    fn comeback_counts_games_won_from_behind() {
+        let rules = GameRules {
+            pot_shuffle: PotShuffle::WinnerFirst,
+            ..GameRules::default()
+        };
+        // Player 0 leads by two cards before player 1 takes every card within nine turns
+        let deck: Deck = "5C 3S 2H 4D 6H 7S".parse().unwrap();
+        let mut comebacks = [Comeback::new(1), Comeback::new(2)];
+        for comeback in &mut comebacks {
+            record_game(comeback, 0, Game::from_deck(deck.clone(), 0, rules.clone()));
+        }
+        assert_eq!((comebacks[0].comebacks, comebacks[0].games), (1, 1));
+        assert_eq!((comebacks[1].comebacks, comebacks[1].games), (0, 1));
+        assert_eq!(comebacks[0].comeback_rate(), 1.0);
+
+        let mut never = Comeback::new(52);
+        record_game(&mut never, 3, Game::seeded(3, GameRules::default()));
+        assert_eq!((never.comebacks, never.games), (0, 1));
+    }
+
+    #[test]
+    fn joint_histogram_puts_a_seeded_game_in_its_bucket() {
+        let joint = || JointHistogram::new(Metric::Length, Metric::WarCount);
+        let single = Simulation::new(1, 42).run_with(joint, |_| {}).unwrap();
+        // Seed 42 takes 188 turns with 13 wars
+        assert_eq!(single.state, HashMap::from([((188, 13), 1)]));
+
+        let many = Simulation {
+            threads: 4,
+            batch_size: 3,
+            ..Simulation::new(30, 42)
+        }
+        .run_with(joint, |_| {})
+        .unwrap();
+        assert_eq!(many.state.values().sum::<u64>(), 30);
+        assert!(many.state[&(188, 13)] >= 1);
+        let mut lengths = State::new();
+        for ((length, _), count) in &many.state {
+            *lengths.entry(*length).or_default() += count;
+        }
+        assert_eq!(lengths, simulate_lengths(30, 42).histogram);
+    }
+
+    #[test]
+    fn every_rng_gives_reproducible_runs_of_its_own() {
+        let algorithms = [
+            RngAlgorithm::Std,
+            RngAlgorithm::ChaCha8,
+            RngAlgorithm::Pcg,
+            RngAlgorithm::SplitMix,
+        ];
+        let histograms: Vec<State> = algorithms
+            .into_iter()
+            .map(|rng| {
+                let simulation = Simulation {
+                    rules: GameRules {
+                        rng,
+                        ..GameRules::default()
+                    },
+                    threads: 2,
+                    ..Simulation::new(60, 5)
+                };
+                let result = simulation.run(|_| {}).unwrap();
+                assert_eq!(result.total_games, 60);
+                assert_eq!(result.histogram, simulation.run(|_| {}).unwrap().histogram);
+                result.histogram
+            })
+            .collect();
+        assert_eq!(histograms[0], simulate_lengths(60, 5).histogram);
+        for (i, a) in histograms.iter().enumerate() {
+            for b in &histograms[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn closeness_buckets_the_losers_cards_before_the_last_turn() {
+        // Seed 7 ends with a war taking the loser's last four cards
+        for (seed, expected) in [(0, 1), (7, 4), (21, 1)] {
+            let mut game = Game::seeded(seed, GameRules::default());
+            let mut before_last = [0; 2];
+            loop {
+                let before = [game.players.0.count_cards(), game.players.1.count_cards()];
+                if game.step().is_none() {
+                    break;
+                }
+                before_last = before;
+            }
+            let loser_cards = before_last[1 - game.winner().unwrap()];
+            assert_eq!(loser_cards, expected);
+            let mut closeness = Closeness::default();
+            record_game(
+                &mut closeness,
+                seed,
+                Game::seeded(seed, GameRules::default()),
+            );
+            assert_eq!(closeness.state, State::from([(loser_cards, 1)]));
+        }
+        let closeness = Simulation::new(50, 0)
+            .run_with(Closeness::default, |_| {})
+            .unwrap();
+        assert_eq!(closeness.state.values().sum::<u64>(), 50);
+        assert!(closeness.state.keys().all(|cards| *cards >= 1));
+    }
+}
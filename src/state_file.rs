@@ -0,0 +1,287 @@
+use std::{
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use crate::{GowsimError, State};
+
+/// Header byte of a state stored as a MessagePack map of value to count
+const SPARSE_HEADER: u8 = 1;
+/// Header byte of a state stored as a little endian `u64` length followed by that many
+/// little endian `u64` counts, one for every value from 0
+const DENSE_HEADER: u8 = 2;
+
+/// Encodings of a [`State`] in a state file, each a version of the file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateEncoding {
+    /// Version 0, a MessagePack map without a header as written before the header existed
+    Legacy,
+    /// Version 1
+    Sparse,
+    /// Version 2
+    Dense,
+}
+
+impl StateEncoding {
+    pub fn version(&self) -> u8 {
+        match *self {
+            StateEncoding::Legacy => 0,
+            StateEncoding::Sparse => SPARSE_HEADER,
+            StateEncoding::Dense => DENSE_HEADER,
+        }
+    }
+
+    /// Dense if the values are packed closely enough for it to be the smaller encoding
+    pub fn for_state(state: &State) -> Self {
+        match state.keys().max() {
+            Some(max) if *max < 4 * state.len() => StateEncoding::Dense,
+            _ => StateEncoding::Sparse,
+        }
+    }
+}
+
+impl FromStr for StateEncoding {
+    type Err = String;
+
+    /// Parses a format version, see [`StateEncoding::version`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<u8>() {
+            Ok(0) => Ok(StateEncoding::Legacy),
+            Ok(SPARSE_HEADER) => Ok(StateEncoding::Sparse),
+            Ok(DENSE_HEADER) => Ok(StateEncoding::Dense),
+            _ => Err(format!(
+                "Unknown state format version `{}`, known are 0, 1 and 2",
+                s
+            )),
+        }
+    }
+}
+
+/// Serializes `state` behind a header byte naming its encoding, no header for
+/// [`StateEncoding::Legacy`]
+pub fn encode_state(state: &State, encoding: StateEncoding) -> Vec<u8> {
+    match encoding {
+        StateEncoding::Legacy => rmp_serde::to_vec(state).unwrap(),
+        StateEncoding::Sparse => {
+            let mut bytes = vec![SPARSE_HEADER];
+            bytes.extend(rmp_serde::to_vec(state).unwrap());
+            bytes
+        }
+        StateEncoding::Dense => {
+            let len = state.keys().max().map_or(0, |max| max + 1);
+            let mut counts = vec![0u64; len];
+            for (value, count) in state {
+                counts[*value] = *count;
+            }
+            let mut bytes = Vec::with_capacity(9 + 8 * len);
+            bytes.push(DENSE_HEADER);
+            bytes.extend((len as u64).to_le_bytes());
+            for count in counts {
+                bytes.extend(count.to_le_bytes());
+            }
+            bytes
+        }
+    }
+}
+
+/// Deserializes a state written by [`encode_state`], or a plain MessagePack map as written
+/// before the header was introduced
+pub fn decode_state(bytes: &[u8]) -> Result<State, GowsimError> {
+    let invalid = |message: &str| GowsimError::Serialization(message.to_string());
+    match bytes.split_first() {
+        Some((&SPARSE_HEADER, rest)) => {
+            rmp_serde::from_slice(rest).map_err(|_| invalid("Malformed sparse state"))
+        }
+        Some((&DENSE_HEADER, rest)) => {
+            let (len, counts) = rest
+                .split_first_chunk::<8>()
+                .ok_or_else(|| invalid("Dense state is missing its length"))?;
+            let expected = usize::try_from(u64::from_le_bytes(*len))
+                .ok()
+                .and_then(|len| len.checked_mul(8))
+                .ok_or_else(|| invalid("Dense state length overflows"))?;
+            if counts.len() != expected {
+                return Err(invalid("Dense state does not match its length"));
+            }
+            Ok(counts
+                .chunks_exact(8)
+                .map(|count| u64::from_le_bytes(count.try_into().unwrap()))
+                .enumerate()
+                .filter(|(_, count)| *count > 0)
+                .collect())
+        }
+        // The first byte of a MessagePack map, fixmap, map 16 or map 32
+        Some((0x80..=0x8f | 0xde | 0xdf, _)) => {
+            rmp_serde::from_slice(bytes).map_err(|_| invalid("Malformed legacy state"))
+        }
+        Some((version, _)) => Err(invalid(&format!(
+            "Unknown state format version {}, written by a newer release?",
+            version
+        ))),
+        None => Err(invalid("Empty state file")),
+    }
+}
+
+/// Sums the dense states read from `inputs` into a dense state written to `output`
+///
+/// Only one count per input is held in memory at a time. Every input must have been written
+/// with [`StateEncoding::Dense`].
+pub fn merge_dense_streaming(
+    mut inputs: Vec<impl Read>,
+    mut output: impl Write,
+) -> Result<(), GowsimError> {
+    let mut lens = Vec::with_capacity(inputs.len());
+    for input in &mut inputs {
+        let mut header = [0u8; 9];
+        input.read_exact(&mut header)?;
+        if header[0] != DENSE_HEADER {
+            return Err(GowsimError::Serialization(
+                "Streaming merges only support dense states".to_string(),
+            ));
+        }
+        lens.push(u64::from_le_bytes(header[1..].try_into().unwrap()));
+    }
+    let len = lens.iter().copied().max().unwrap_or(0);
+    output.write_all(&[DENSE_HEADER])?;
+    output.write_all(&len.to_le_bytes())?;
+    for position in 0..len {
+        let mut total: u64 = 0;
+        for (input, input_len) in inputs.iter_mut().zip(&lens) {
+            if position < *input_len {
+                let mut count = [0u8; 8];
+                input.read_exact(&mut count)?;
+                total = total.saturating_add(u64::from_le_bytes(count));
+            }
+        }
+        output.write_all(&total.to_le_bytes())?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODINGS: [StateEncoding; 3] = [
+        StateEncoding::Legacy,
+        StateEncoding::Sparse,
+        StateEncoding::Dense,
+    ];
+
+    #[test]
+    fn every_encoding_round_trips_to_the_same_state() {
+        let state = State::from([(0, 3), (7, 1), (120, u64::MAX), (121, 40)]);
+        for encoding in ENCODINGS {
+            let decoded = decode_state(&encode_state(&state, encoding)).unwrap();
+            assert_eq!(decoded, state, "{:?}", encoding);
+        }
+        for encoding in ENCODINGS {
+            assert_eq!(
+                decode_state(&encode_state(&State::new(), encoding)).unwrap(),
+                State::new()
+            );
+        }
+    }
+
+    #[test]
+    fn headers_name_the_encoding() {
+        let state = State::from([(2, 5)]);
+        assert_eq!(encode_state(&state, StateEncoding::Sparse)[0], 1);
+        assert_eq!(encode_state(&state, StateEncoding::Dense)[0], 2);
+        assert_eq!(
+            encode_state(&state, StateEncoding::Legacy),
+            rmp_serde::to_vec(&state).unwrap()
+        );
+        for encoding in ENCODINGS {
+            assert_eq!(encoding.version().to_string().parse(), Ok(encoding));
+        }
+    }
+
+    #[test]
+    fn dense_is_chosen_for_closely_packed_values() {
+        let packed: State = (40..200).map(|length| (length, 1)).collect();
+        assert_eq!(StateEncoding::for_state(&packed), StateEncoding::Dense);
+        let spread = State::from([(5, 1), (10_000, 1)]);
+        assert_eq!(StateEncoding::for_state(&spread), StateEncoding::Sparse);
+    }
+
+    #[test]
+    fn malformed_states_are_rejected() {
+        let mut truncated = encode_state(&State::from([(3, 1)]), StateEncoding::Dense);
+        truncated.pop();
+        let mut overflowing = vec![2];
+        overflowing.extend(u64::MAX.to_le_bytes());
+        for bytes in [&[][..], &[7, 1, 2], &[2, 1], &truncated, &overflowing] {
+            assert!(matches!(
+                decode_state(bytes),
+                Err(GowsimError::Serialization(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn streaming_merge_matches_the_in_memory_merge() {
+        let states = [
+            State::from([(3, 1), (10, 4)]),
+            State::from([(0, 2), (10, u64::MAX), (40, 7)]),
+            State::new(),
+            State::from([(3, 5)]),
+        ];
+        let inputs: Vec<Vec<u8>> = states
+            .iter()
+            .map(|state| encode_state(state, StateEncoding::Dense))
+            .collect();
+        let mut merged = Vec::new();
+        merge_dense_streaming(inputs.iter().map(Vec::as_slice).collect(), &mut merged).unwrap();
+        let mut expected = State::new();
+        for state in states {
+            crate::merge_states(&mut expected, state);
+        }
+        assert_eq!(decode_state(&merged).unwrap(), expected);
+        assert_eq!(merged, encode_state(&expected, StateEncoding::Dense));
+    }
+
+    #[test]
+    fn streaming_merge_rejects_sparse_states() {
+        let state: State = (100..110).map(|length| (length, 1)).collect();
+        let sparse = encode_state(&state, StateEncoding::Sparse);
+        let result = merge_dense_streaming(vec![sparse.as_slice()], Vec::new());
+        assert!(matches!(result, Err(GowsimError::Serialization(_))));
+    }
+
+    #[test]
+    fn every_known_version_loads() {
+        let expected = State::from([(3, 2)]);
+        // The dense header is followed by the number of counts
+        let mut dense = vec![2];
+        for count in [4u64, 0, 0, 0, 2] {
+            dense.extend(count.to_le_bytes());
+        }
+        // A MessagePack map of 3 to 2, then the same behind the sparse header
+        for bytes in [&[0x81, 3, 2][..], &[1, 0x81, 3, 2], &dense] {
+            assert_eq!(decode_state(bytes).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn unknown_versions_are_named_in_the_error() {
+        for version in [3u8, 0x40, 0xff] {
+            match decode_state(&[version, 0x81, 3, 2]) {
+                Err(GowsimError::Serialization(message)) => assert_eq!(
+                    message,
+                    format!(
+                        "Unknown state format version {}, written by a newer release?",
+                        version
+                    )
+                ),
+                other => panic!("{:?}", other),
+            }
+        }
+        assert_eq!(
+            "3".parse::<StateEncoding>(),
+            Err("Unknown state format version `3`, known are 0, 1 and 2".to_string())
+        );
+        assert!("dense".parse::<StateEncoding>().is_err());
+    }
+}
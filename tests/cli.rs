@@ -0,0 +1,475 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// Fresh empty directory for a test to run the binary in, so its state files stay apart
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gowsim-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn gowsim(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gowsim"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn progress_json_lines_are_json_objects_with_the_progress_keys() {
+    let dir = temp_dir("progress-json");
+    // Progress is reported every 5 seconds, so the run has to last longer than that
+    let output = gowsim(
+        &dir,
+        &[
+            "-n",
+            "100000000",
+            "--seed",
+            "1",
+            "--limit-time",
+            "6",
+            "--no-save",
+            "--progress-json",
+        ],
+    );
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert!(!lines.is_empty());
+    for line in lines {
+        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+        let object = value.as_object().unwrap();
+        let mut keys: Vec<&str> = object.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["elapsed", "eta_secs", "games_done", "throughput"]);
+        assert!(object.values().all(serde_json::Value::is_number));
+    }
+}
+
+#[test]
+fn zero_games_write_nothing() {
+    let dir = temp_dir("zero-no-save");
+    let output = gowsim(&dir, &["-n", "0", "--no-save"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Nothing to simulate"));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn zero_games_leave_the_saved_state_untouched() {
+    let dir = temp_dir("zero-saved");
+    assert!(gowsim(&dir, &["-n", "20", "--seed", "1"]).status.success());
+    let csv = fs::read(dir.join("state.csv")).unwrap();
+    let msgp = fs::read(dir.join("state.msgp")).unwrap();
+    for args in [&["-n", "0"][..], &["--seed-start", "5", "--seed-end", "5"]] {
+        let output = gowsim(&dir, args);
+        assert!(output.status.success());
+        assert!(String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Nothing to simulate"));
+        assert_eq!(fs::read(dir.join("state.csv")).unwrap(), csv);
+        assert_eq!(fs::read(dir.join("state.msgp")).unwrap(), msgp);
+    }
+}
+
+#[test]
+fn stdout_holds_only_the_histogram() {
+    let dir = temp_dir("stdout");
+    let output = gowsim(&dir, &["-n", "50", "--seed", "3", "--stdout"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("length,count"));
+    let total: u64 = lines
+        .map(|line| {
+            let (length, count) = line.split_once(',').unwrap();
+            length.parse::<usize>().unwrap();
+            count.parse::<u64>().unwrap()
+        })
+        .sum();
+    assert_eq!(total, 50);
+    assert!(!output.stderr.is_empty());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    let output = gowsim(
+        &dir,
+        &["-n", "50", "--seed", "3", "--stdout", "--format", "json"],
+    );
+    assert!(output.status.success());
+    let rows: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let total: u64 = rows.iter().map(|row| row["count"].as_u64().unwrap()).sum();
+    assert_eq!(total, 50);
+    assert!(rows.iter().all(|row| row["length"].is_u64()));
+}
+
+/// Splits CSV lines into their fields, checking they hold no quotes or stray whitespace
+fn csv_rows(csv: &str, delimiter: char) -> Vec<Vec<&str>> {
+    csv.lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.split(delimiter).collect();
+            for field in &fields {
+                assert!(!field.is_empty(), "Empty field in {:?}", line);
+                assert!(
+                    !field.contains(|c: char| c.is_whitespace() || c == '"'),
+                    "Stray characters in {:?}",
+                    line
+                );
+            }
+            fields
+        })
+        .collect()
+}
+
+#[test]
+fn csv_histogram_is_strictly_comma_separated() {
+    let dir = temp_dir("csv");
+    assert!(gowsim(&dir, &["-n", "30", "--seed", "8"]).status.success());
+    let csv = fs::read_to_string(dir.join("state.csv")).unwrap();
+    let rows = csv_rows(&csv, ',');
+    assert_eq!(rows[0], ["length", "count"]);
+    assert!(rows[1..].iter().all(|row| row.len() == 2));
+    let total: u64 = rows[1..]
+        .iter()
+        .map(|row| row[1].parse::<u64>().unwrap())
+        .sum();
+    assert_eq!(total, 30);
+}
+
+#[test]
+fn csv_delimiter_and_header_are_configurable() {
+    let dir = temp_dir("csv-delimiter");
+    let output = gowsim(
+        &dir,
+        &[
+            "-n",
+            "30",
+            "--seed",
+            "8",
+            "--stdout",
+            "--delimiter",
+            ";",
+            "--no-header",
+        ],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows = csv_rows(&stdout, ';');
+    assert!(rows.iter().all(|row| row.len() == 2));
+    let total: u64 = rows.iter().map(|row| row[1].parse::<u64>().unwrap()).sum();
+    assert_eq!(total, 30);
+}
+
+fn stdout_lines(output: &Output) -> Vec<String> {
+    assert!(output.status.success());
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn inspect_prints_the_stats_of_the_seeded_game() {
+    let dir = temp_dir("inspect");
+    let mut game = gowsim::game::Game::seeded(12345, Default::default());
+    game.play_to_completion();
+    let lines = stdout_lines(&gowsim(&dir, &["inspect", "--seed", "12345"]));
+    assert_eq!(lines[0], "Game with seed 12345");
+    assert!(lines.contains(&format!("Turns: {}", game.stats.turn_number)));
+    assert!(lines.contains(&format!("Wars: {}", game.stats.war_count)));
+
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &[
+            "--no-shuffle-deal",
+            "--no-shuffle",
+            "inspect",
+            "--seed",
+            "3",
+        ],
+    ));
+    for line in ["Turns: 190", "Wars: 13", "Winner: Player 0"] {
+        assert!(lines.iter().any(|printed| printed == line), "{}", line);
+    }
+}
+
+#[test]
+fn player_names_appear_in_the_summary() {
+    let dir = temp_dir("player-names");
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &["-n", "20", "--no-save", "--player-names", "Alice,Bob"],
+    ));
+    assert!(lines.iter().any(|line| line.contains("Alice won")));
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &["--player-names", "Alice,Bob", "inspect", "--seed", "12345"],
+    ));
+    assert!(lines.iter().any(|line| line == "Winner: Bob"));
+}
+
+#[test]
+fn warmup_games_are_not_counted() {
+    let dir = temp_dir("warmup");
+    let plain = gowsim(&dir, &["-n", "30", "--seed", "2", "--stdout"]);
+    let warmed_up = gowsim(
+        &dir,
+        &["-n", "30", "--seed", "2", "--stdout", "--warmup", "50"],
+    );
+    assert!(plain.status.success() && warmed_up.status.success());
+    assert_eq!(warmed_up.stdout, plain.stdout);
+    assert!(String::from_utf8(warmed_up.stderr)
+        .unwrap()
+        .contains("Warming up with 50 games"));
+}
+
+#[test]
+fn output_dir_is_created_with_the_state_and_manifest() {
+    let dir = temp_dir("output-dir");
+    let output = gowsim(
+        &dir,
+        &["-n", "10", "--seed", "4", "--output-dir", "runs/experiment"],
+    );
+    assert!(output.status.success());
+    let runs: Vec<PathBuf> = fs::read_dir(dir.join("runs/experiment"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(runs.len(), 1);
+    let run = &runs[0];
+    let name = run.file_name().unwrap().to_str().unwrap();
+    assert!(name.ends_with("-seed4-10games-length"), "{}", name);
+    for file in ["manifest.json", "state.csv", "state.msgp"] {
+        assert!(run.join(file).is_file(), "{} is missing", file);
+    }
+    assert!(!dir.join("state.csv").exists());
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&fs::read(run.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["base_seed"], 4);
+    assert_eq!(manifest["num_games"], 10);
+    assert_eq!(manifest["metric"], "length");
+}
+
+#[test]
+fn handicap_sweep_has_a_row_per_step_with_rising_win_rates() {
+    let dir = temp_dir("handicap-sweep");
+    let output = gowsim(
+        &dir,
+        &[
+            "handicap-sweep",
+            "--num",
+            "100",
+            "--step",
+            "5",
+            "--max-handicap",
+            "25",
+        ],
+    );
+    let csv = stdout_lines(&output).join("\n");
+    let rows = csv_rows(&csv, ',');
+    assert_eq!(rows[0], ["handicap", "player0_win_rate", "mean_length"]);
+    assert_eq!(rows.len(), 7);
+    let win_rates: Vec<f64> = rows[1..]
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            assert_eq!(row[0], (index * 5).to_string());
+            row[1].parse().unwrap()
+        })
+        .collect();
+    // Random games make neighbouring handicaps noisy, but never by this much
+    assert!(win_rates.windows(2).all(|pair| pair[1] > pair[0] - 0.1));
+    assert!(win_rates[5] > 0.9 && win_rates[0] < 0.6);
+}
+
+#[test]
+fn qq_pairs_the_quantiles_of_two_states() {
+    use gowsim::state_file::{encode_state, StateEncoding};
+    let dir = temp_dir("qq");
+    let a = gowsim::State::from([(10, 1), (20, 1), (30, 1), (40, 1)]);
+    let b = gowsim::State::from([(100, 2), (200, 2)]);
+    fs::write(dir.join("a.msgp"), encode_state(&a, StateEncoding::Sparse)).unwrap();
+    fs::write(dir.join("b.msgp"), encode_state(&b, StateEncoding::Dense)).unwrap();
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &["--precision", "0", "qq", "a.msgp", "b.msgp", "--steps", "4"],
+    ));
+    assert_eq!(
+        lines,
+        [
+            "quantile,a,b",
+            "0.00,10,100",
+            "0.25,10,100",
+            "0.50,20,100",
+            "0.75,30,200",
+            "1.00,40,200"
+        ]
+    );
+}
+
+#[test]
+fn snapshots_are_saved_at_the_given_counts() {
+    use gowsim::state_file::decode_state;
+    let dir = temp_dir("snapshots");
+    let output = gowsim(
+        &dir,
+        &[
+            "-n",
+            "100",
+            "--seed",
+            "6",
+            "--snapshot-at",
+            "1e1,30,100,500",
+        ],
+    );
+    assert!(output.status.success());
+    let read = |stem: &str| decode_state(&fs::read(dir.join(format!("{}.msgp", stem))).unwrap());
+    for count in [10, 30, 100] {
+        let snapshot = read(&format!("state_at{}", count)).unwrap();
+        assert_eq!(snapshot.values().sum::<u64>(), count);
+        assert!(dir.join(format!("state_at{}.csv", count)).is_file());
+    }
+    assert!(!dir.join("state_at500.msgp").exists());
+    assert_eq!(read("state_at100").unwrap(), read("state").unwrap());
+
+    // A snapshot holds the same games as a run stopping there
+    let plain = temp_dir("snapshots-plain");
+    assert!(gowsim(&plain, &["-n", "30", "--seed", "6"])
+        .status
+        .success());
+    assert_eq!(
+        fs::read(dir.join("state_at30.csv")).unwrap(),
+        fs::read(plain.join("state.csv")).unwrap()
+    );
+}
+
+#[test]
+fn time_limit_stops_the_run_and_saves_the_games_played() {
+    let dir = temp_dir("limit-time");
+    let started = std::time::Instant::now();
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &[
+            "--limit-time",
+            "0.3",
+            "--seed",
+            "2",
+            "--batch-size",
+            "100",
+            "--threads",
+            "1",
+        ],
+    ));
+    assert!(started.elapsed().as_secs() < 10);
+    let stopped = lines
+        .iter()
+        .find_map(|line| line.strip_prefix("Stopped after the time limit of 0.3 seconds with "))
+        .unwrap();
+    let games: u64 = stopped.strip_suffix(" games").unwrap().parse().unwrap();
+    // The time is checked after every batch
+    assert!(games > 0 && games.is_multiple_of(100), "{}", games);
+    let csv = fs::read_to_string(dir.join("state.csv")).unwrap();
+    let total: u64 = csv_rows(&csv, ',')[1..]
+        .iter()
+        .map(|row| row[1].parse::<u64>().unwrap())
+        .sum();
+    assert_eq!(total, games);
+
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &["-n", "300", "--limit-time", "60", "--no-save"],
+    ));
+    assert!(!lines.iter().any(|line| line.starts_with("Stopped")));
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_histogram_reads_back_with_a_row_per_length() {
+    use parquet::{
+        file::reader::{FileReader, SerializedFileReader},
+        record::RowAccessor,
+    };
+
+    let dir = temp_dir("parquet");
+    let output = gowsim(
+        &dir,
+        &[
+            "-n",
+            "500",
+            "--seed",
+            "3",
+            "--no-save",
+            "--parquet",
+            "lengths.parquet",
+        ],
+    );
+    assert!(output.status.success());
+    let file = fs::File::open(dir.join("lengths.parquet")).unwrap();
+    let reader = SerializedFileReader::new(file).unwrap();
+    let names: Vec<_> = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    assert_eq!(names, ["length", "count"]);
+    let rows: Vec<(i64, i64)> = reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|row| {
+            let row = row.unwrap();
+            (row.get_long(0).unwrap(), row.get_long(1).unwrap())
+        })
+        .collect();
+    assert!(rows.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(rows.iter().map(|(_, count)| count).sum::<i64>(), 500);
+}
+
+#[test]
+fn compare_shuffle_names_the_last_seed_played_at_the_end_of_the_seeds() {
+    let dir = temp_dir("compare-shuffle-banner");
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &[
+            "compare-shuffle",
+            "--seed-start",
+            "18446744073709551612",
+            "--seed-end",
+            "18446744073709551615",
+            "--max-turns",
+            "100",
+        ],
+    ));
+    assert_eq!(
+        lines[0],
+        "Playing seeds 18446744073709551612 to 18446744073709551614 for at most 100 turns"
+    );
+}
+
+#[test]
+fn compare_deals_names_the_last_seed_dealt_at_the_end_of_the_seeds() {
+    let dir = temp_dir("compare-deals-banner");
+    let lines = stdout_lines(&gowsim(
+        &dir,
+        &[
+            "compare-deals",
+            "--seed-start",
+            "18446744073709551612",
+            "--seed-end",
+            "18446744073709551615",
+        ],
+    ));
+    assert_eq!(
+        lines[0],
+        "Dealing the decks of seeds 18446744073709551612 to 18446744073709551614"
+    );
+}